@@ -0,0 +1,59 @@
+//! 最近邻缩放基准：`resize_nearest` 与 `DynamicImage::resize_exact`
+//!
+//! Nearest-neighbour resize benchmark comparing `resize_nearest` with
+//! `DynamicImage::resize_exact`. Run with `cargo bench --bench resize`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use i3f::image::resize_nearest;
+use image::imageops::FilterType;
+use image::{DynamicImage, RgbaImage};
+
+/// 重复执行直到累计耗时超过一秒，返回单次平均耗时
+fn measure(mut f: impl FnMut()) -> Duration {
+    f();
+    let start = Instant::now();
+    let mut iterations = 0;
+    while start.elapsed() < Duration::from_secs(1) {
+        f();
+        iterations += 1;
+    }
+    start.elapsed() / iterations
+}
+
+fn main() {
+    let source = RgbaImage::from_fn(4096, 4096, |x, y| {
+        image::Rgba([x as u8, y as u8, (x ^ y) as u8, (x + y) as u8])
+    });
+    let source = DynamicImage::ImageRgba8(source);
+    let images = [
+        ("L8", DynamicImage::ImageLuma8(source.to_luma8())),
+        ("RGB8", DynamicImage::ImageRgb8(source.to_rgb8())),
+        ("RGBA8", source),
+    ];
+    let sizes = [(1024, 1024), (256, 256), (6000, 6000)];
+
+    println!(
+        "{:<6} {:>11} {:>12} {:>12} {:>8}",
+        "type", "size", "image", "fast", "speedup"
+    );
+    for (name, image) in &images {
+        for (width, height) in sizes {
+            let baseline = measure(|| {
+                black_box(black_box(image).resize_exact(width, height, FilterType::Nearest));
+            });
+            let fast = measure(|| {
+                black_box(resize_nearest(black_box(image), width, height));
+            });
+            println!(
+                "{:<6} {:>11} {:>12.2?} {:>12.2?} {:>7.1}x",
+                name,
+                format!("{width}x{height}"),
+                baseline,
+                fast,
+                baseline.as_secs_f64() / fast.as_secs_f64()
+            );
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! IIIF 图像服务客户端(IIIF Image Service Client)
+//!
+//! 读取远程 `info.json`，在服务的尺寸限制内规划瓦片请求，并发下载后拼接为完整分辨率的图像。
+//!
+//! Fetches a remote image information document, plans tile requests within the limits of the
+//! service, downloads them concurrently and stitches them into a full-resolution image.
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use image::{DynamicImage, GenericImageView, RgbaImage};
+
+use crate::IiifError;
+use crate::image::{Format, IiifImage, ImageInfo, Limits, Rect, TileGrid, TileInfo};
+
+/// 获取远程资源的接口
+///
+/// Fetches the bytes behind a URL. Implemented by `HttpFetcher` with the `client` feature,
+/// and by any other transport or stand-in.
+pub trait Fetcher: Sync {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, IiifError>;
+}
+
+/// 基于 `ureq` 的 HTTP 客户端
+///
+/// A blocking HTTP fetcher backed by `ureq`.
+///
+/// Example:
+/// ```no_run
+/// use i3f::client::{HttpFetcher, ImageClient};
+///
+/// let client = ImageClient::new(HttpFetcher::default());
+/// let info = client.fetch_info("https://example.org/iiif/demo.jpg").unwrap();
+/// client.download_to_file(&info, "demo.png").unwrap();
+/// ```
+#[cfg(feature = "client")]
+#[derive(Debug, Clone)]
+pub struct HttpFetcher {
+    agent: ureq::Agent,
+}
+
+#[cfg(feature = "client")]
+impl Default for HttpFetcher {
+    fn default() -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new()
+                .user_agent(concat!("i3f/", env!("CARGO_PKG_VERSION")))
+                .build(),
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl Fetcher for HttpFetcher {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, IiifError> {
+        use std::io::Read;
+
+        let response = self.agent.get(url).call().map_err(|e| match e {
+            ureq::Error::Status(404, _) => IiifError::NotFound(format!("{url}: {e}")),
+            ureq::Error::Status(401, _) => IiifError::Unauthorized(format!("{url}: {e}")),
+            ureq::Error::Status(403, _) => IiifError::Forbidden(format!("{url}: {e}")),
+            ureq::Error::Status(503, _) => IiifError::ServiceUnavailable(format!("{url}: {e}")),
+            e => IiifError::InternalServerError(format!("{url}: {e}")),
+        })?;
+        let mut data = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut data)
+            .map_err(|e| IiifError::InternalServerError(format!("{url}: {e}")))?;
+        Ok(data)
+    }
+}
+
+/// 单个瓦片请求
+///
+/// A request for one piece of the full-resolution image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileRequest {
+    /// 瓦片在原图中的区域
+    pub region: Rect,
+
+    /// 瓦片的 URL
+    pub url: String,
+}
+
+/// 图像服务客户端
+///
+/// A client downloading full-resolution images through an image service.
+#[derive(Debug, Clone)]
+pub struct ImageClient<F: Fetcher> {
+    fetcher: F,
+    concurrency: usize,
+    format: Format,
+}
+
+impl<F: Fetcher> ImageClient<F> {
+    pub fn new(fetcher: F) -> Self {
+        Self {
+            fetcher,
+            concurrency: 4,
+            format: Format::Jpg,
+        }
+    }
+
+    /// 并发下载的数量
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// 瓦片使用的格式，默认为 `jpg`
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// 获取图像信息，`url` 可以是图像的基础 URI 或 `info.json` 的 URL
+    ///
+    /// Fetches the image information, `url` being either the base URI of the image or the URL
+    /// of its `info.json`.
+    pub fn fetch_info(&self, url: &str) -> Result<ImageInfo, IiifError> {
+        let url = if url.ends_with("/info.json") {
+            url.to_string()
+        } else {
+            format!("{}/info.json", url.trim_end_matches('/'))
+        };
+        let data = self.fetcher.fetch(&url)?;
+        serde_json::from_slice(&data)
+            .map_err(|e| IiifError::InternalServerError(format!("Invalid info.json: {e}")))
+    }
+
+    /// 规划完整分辨率下载所需的请求
+    ///
+    /// Plans the requests covering the full image at full resolution. The advertised tiles are
+    /// used when they fit the limits of the service, otherwise the image is split into the
+    /// largest pieces the limits allow.
+    pub fn plan(&self, info: &ImageInfo) -> Result<Vec<TileRequest>, IiifError> {
+        if info.width == 0 || info.height == 0 {
+            return Err(IiifError::InternalServerError(
+                "Image has no dimensions".to_string(),
+            ));
+        }
+        let limits = Limits::from_info(info);
+        let tile = info
+            .tiles
+            .iter()
+            .flatten()
+            .find(|tile| {
+                // 忽略尺寸或缩放因子为 0 的无效分块
+                let height = tile.height.unwrap_or(tile.width);
+                tile.width > 0
+                    && height > 0
+                    && tile.scale_factors.contains(&1)
+                    && !tile.scale_factors.contains(&0)
+                    && limits.allows(tile.width, height)
+            })
+            .cloned()
+            .unwrap_or_else(|| {
+                let (width, height) = limits.fit(info.width, info.height);
+                TileInfo {
+                    r#type: None,
+                    scale_factors: vec![1],
+                    width,
+                    height: Some(height),
+                }
+            });
+        let grid = TileGrid::new(info.width, info.height, &tile);
+        Ok(grid
+            .tiles(1)
+            .map(|tile| {
+                let image = IiifImage {
+                    format: self.format.clone(),
+                    ..grid.tile_image(&tile, "")
+                };
+                TileRequest {
+                    region: tile.region,
+                    url: format!(
+                        "{}/{}/{}/{}/{}.{}",
+                        info.id.trim_end_matches('/'),
+                        image.region,
+                        image.size,
+                        image.rotation,
+                        image.quality,
+                        image.format
+                    ),
+                }
+            })
+            .collect())
+    }
+
+    /// 下载并拼接完整分辨率的图像
+    ///
+    /// Downloads the planned requests concurrently and stitches them into the full image.
+    pub fn download(&self, info: &ImageInfo) -> Result<DynamicImage, IiifError> {
+        let requests = self.plan(info)?;
+        let canvas = Mutex::new(RgbaImage::new(info.width, info.height));
+        let next = AtomicUsize::new(0);
+        let error = Mutex::new(None);
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.concurrency.min(requests.len()) {
+                scope.spawn(|| {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(request) = requests.get(index) else {
+                            break;
+                        };
+                        if error.lock().unwrap().is_some() {
+                            break;
+                        }
+                        match self.fetch_tile(request) {
+                            Ok(tile) => image::imageops::replace(
+                                &mut *canvas.lock().unwrap(),
+                                &tile,
+                                i64::from(request.region.x),
+                                i64::from(request.region.y),
+                            ),
+                            Err(e) => {
+                                error.lock().unwrap().get_or_insert(e);
+                                break;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        if let Some(e) = error.into_inner().unwrap() {
+            return Err(e);
+        }
+        Ok(DynamicImage::ImageRgba8(canvas.into_inner().unwrap()))
+    }
+
+    /// 下载完整分辨率的图像并保存到文件，格式由扩展名决定
+    pub fn download_to_file(
+        &self,
+        info: &ImageInfo,
+        path: impl AsRef<Path>,
+    ) -> Result<(), IiifError> {
+        self.download(info)?
+            .save(path)
+            .map_err(|e| IiifError::InternalServerError(e.to_string()))
+    }
+
+    fn fetch_tile(&self, request: &TileRequest) -> Result<RgbaImage, IiifError> {
+        let data = self.fetcher.fetch(&request.url)?;
+        let tile = image::load_from_memory(&data)
+            .map_err(|e| IiifError::InternalServerError(format!("{}: {e}", request.url)))?;
+        if tile.dimensions() != (request.region.width, request.region.height) {
+            return Err(IiifError::InternalServerError(format!(
+                "{}: expected {}x{} pixels, got {}x{}",
+                request.url,
+                request.region.width,
+                request.region.height,
+                tile.width(),
+                tile.height()
+            )));
+        }
+        Ok(tile.to_rgba8())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use url::Url;
+
+    use crate::image::{ImageRequest, PyramidConfig, RequestParser, ServiceConfig};
+    use crate::storage::{LocalStorage, Storage};
+
+    use super::*;
+
+    /// 本地替身服务：直接在进程内处理请求，不缓存衍生图
+    struct StandIn {
+        config: ServiceConfig,
+        requests: AtomicUsize,
+    }
+
+    struct NoCache(LocalStorage);
+
+    impl Storage for NoCache {
+        fn get_origin_file(&self, identifier: &str) -> Result<Vec<u8>, String> {
+            self.0.get_origin_file(identifier)
+        }
+
+        fn get_iiif_file(&self, _: &IiifImage) -> Result<crate::image::ProcessResult, String> {
+            Err("not cached".to_string())
+        }
+
+        fn save_iiif_file(&self, _: &IiifImage, _: &[u8]) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn cache_key(&self) -> String {
+            self.0.cache_key()
+        }
+    }
+
+    impl Fetcher for StandIn {
+        fn fetch(&self, url: &str) -> Result<Vec<u8>, IiifError> {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            let url = Url::parse(url).unwrap();
+            let storage = NoCache(LocalStorage::new("./fixtures", "./fixtures/out"));
+            match RequestParser::new("https://example.org/iiif").parse_url(&url)? {
+                ImageRequest::Image(image) => Ok(image.process_with(&storage, &self.config)?.data),
+                ImageRequest::Info { identifier } => {
+                    let info = self.config.image_info(
+                        &format!("https://example.org/iiif/{identifier}"),
+                        300,
+                        200,
+                    );
+                    Ok(serde_json::to_vec(&info).unwrap())
+                }
+                ImageRequest::Base { .. } => Err(IiifError::NotFound(url.to_string())),
+            }
+        }
+    }
+
+    fn stand_in(limits: Limits, pyramid: Option<PyramidConfig>) -> StandIn {
+        StandIn {
+            config: ServiceConfig {
+                limits,
+                pyramid,
+                ..Default::default()
+            },
+            requests: AtomicUsize::new(0),
+        }
+    }
+
+    fn original() -> RgbaImage {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let data = storage.get_origin_file("demo.jpg").unwrap();
+        image::load_from_memory(&data).unwrap().to_rgba8()
+    }
+
+    #[test]
+    fn test_download_with_tiles() {
+        let pyramid = PyramidConfig {
+            tile_width: 128,
+            ..Default::default()
+        };
+        let limits = Limits {
+            max_width: Some(150),
+            ..Default::default()
+        };
+        let client = ImageClient::new(stand_in(limits, Some(pyramid))).with_format(Format::Png);
+        let info = client
+            .fetch_info("https://example.org/iiif/demo.jpg")
+            .unwrap();
+        assert_eq!(info.max_width, Some(150));
+
+        let requests = client.plan(&info).unwrap();
+        assert_eq!(requests.len(), 6);
+        assert_eq!(
+            requests[5].url,
+            "https://example.org/iiif/demo.jpg/256,128,44,72/max/0/default.png"
+        );
+
+        let image = client.download(&info).unwrap();
+        assert_eq!(image.to_rgba8(), original());
+        assert_eq!(client.fetcher.requests.load(Ordering::Relaxed), 7);
+    }
+
+    #[test]
+    fn test_download_without_tiles() {
+        let limits = Limits {
+            max_area: Some(10_000),
+            ..Default::default()
+        };
+        let client = ImageClient::new(stand_in(limits, None))
+            .with_format(Format::Png)
+            .with_concurrency(8);
+        let info = client
+            .fetch_info("https://example.org/iiif/demo.jpg/info.json")
+            .unwrap();
+        let requests = client.plan(&info).unwrap();
+        assert!(requests.len() > 1);
+        for request in &requests {
+            assert!(u64::from(request.region.width * request.region.height) <= 10_000);
+        }
+        assert_eq!(client.download(&info).unwrap().to_rgba8(), original());
+    }
+
+    #[test]
+    fn test_plan_malformed_tiles() {
+        let client = ImageClient::new(stand_in(Limits::default(), None)).with_format(Format::Png);
+        let tile = |width, height, scale_factors: &[u8]| TileInfo {
+            r#type: None,
+            scale_factors: scale_factors.to_vec(),
+            width,
+            height,
+        };
+        let mut info =
+            ServiceConfig::default().image_info("https://example.org/iiif/demo.jpg", 300, 200);
+        info.tiles = Some(vec![
+            tile(0, None, &[1, 2]),
+            tile(128, Some(0), &[1]),
+            tile(0, Some(128), &[1]),
+            tile(64, None, &[0, 1]),
+        ]);
+        let requests = client.plan(&info).unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].url,
+            "https://example.org/iiif/demo.jpg/full/max/0/default.png"
+        );
+    }
+
+    #[test]
+    fn test_download_error() {
+        let client = ImageClient::new(stand_in(Limits::default(), None)).with_format(Format::Pdf);
+        let info = client
+            .fetch_info("https://example.org/iiif/demo.jpg")
+            .unwrap();
+        assert!(client.download(&info).is_err());
+
+        let client = ImageClient::new(stand_in(Limits::default(), None));
+        assert!(matches!(
+            client.fetch_info("https://example.org/other/demo.jpg"),
+            Err(IiifError::NotFound(_))
+        ));
+    }
+}
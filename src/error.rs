@@ -1,7 +1,7 @@
 use thiserror::Error;
 
 /// IiifError 定义了 IIIF 相关的错误类型
-#[derive(Debug, Error, PartialEq)]
+#[derive(Debug, Clone, Error, PartialEq)]
 pub enum IiifError {
     #[error("Invalid IIIF image URL: {0}")]
     InvalidIiifURL(String),
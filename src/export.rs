@@ -0,0 +1,330 @@
+//! Deep Zoom 与 Zoomify 金字塔导出(Deep Zoom and Zoomify Pyramid Export)
+//!
+//! 使用与 IIIF 请求相同的 `Region`/`Size`/`Format` 处理流程，从同一张原图生成旧版查看器所需的瓦片金字塔。
+//!
+//! Writes the tile pyramids understood by legacy viewers from the same source image, using the
+//! `Region`/`Size`/`Format` pipeline of IIIF requests.
+use std::fs;
+use std::path::Path;
+
+use image::DynamicImage;
+
+use crate::IiifError;
+use crate::image::{Format, Region, Size};
+
+/// Deep Zoom (DZI) 导出
+///
+/// Exports a [Deep Zoom](https://learn.microsoft.com/en-us/previous-versions/windows/silverlight/dotnet-windows-silverlight/cc645077(v=vs.95))
+/// pyramid: a `{name}.dzi` descriptor and `{name}_files/{level}/{col}_{row}.{format}` tiles.
+///
+/// Example:
+/// ```
+/// use i3f::export::DeepZoom;
+///
+/// let dzi = DeepZoom::default();
+/// assert_eq!(dzi.max_level(300, 200), 9);
+/// assert_eq!(dzi.level_size(300, 200, 8), (150, 100));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeepZoom {
+    /// 瓦片尺寸（不含重叠）
+    pub tile_size: u32,
+
+    /// 瓦片之间的重叠像素
+    pub overlap: u32,
+
+    /// 瓦片格式
+    pub format: Format,
+}
+
+impl Default for DeepZoom {
+    fn default() -> Self {
+        Self {
+            tile_size: 254,
+            overlap: 1,
+            format: Format::Jpg,
+        }
+    }
+}
+
+impl DeepZoom {
+    /// 最高层级，该层级为原图尺寸，层级 0 为 1x1
+    pub fn max_level(&self, width: u32, height: u32) -> u32 {
+        let max = width.max(height);
+        if max <= 1 {
+            0
+        } else {
+            u32::BITS - (max - 1).leading_zeros()
+        }
+    }
+
+    /// 某个层级的图像尺寸
+    pub fn level_size(&self, width: u32, height: u32, level: u32) -> (u32, u32) {
+        let shift = self.max_level(width, height) - level;
+        let scale = 1u64 << shift;
+        (
+            (u64::from(width).div_ceil(scale)) as u32,
+            (u64::from(height).div_ceil(scale)) as u32,
+        )
+    }
+
+    /// 生成 `.dzi` 描述文件
+    pub fn descriptor(&self, width: u32, height: u32) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" Format=\"{}\" Overlap=\"{}\" TileSize=\"{}\">\n  \
+             <Size Width=\"{width}\" Height=\"{height}\"/>\n\
+             </Image>\n",
+            self.format, self.overlap, self.tile_size
+        )
+    }
+
+    /// 将图像导出到 `dir` 目录下，生成 `{name}.dzi` 和 `{name}_files`
+    ///
+    /// Writes `{name}.dzi` and the `{name}_files` pyramid of `image` into `dir`.
+    pub fn export(&self, image: &DynamicImage, dir: &Path, name: &str) -> Result<(), IiifError> {
+        let (width, height) = (image.width(), image.height());
+        let tile_size = self.tile_size.max(1);
+        let files = dir.join(format!("{name}_files"));
+        let mut level_image = image.clone();
+        for level in (0..=self.max_level(width, height)).rev() {
+            let (lw, lh) = self.level_size(width, height, level);
+            level_image = resize(level_image, lw, lh)?;
+            let level_dir = files.join(level.to_string());
+            create_dir(&level_dir)?;
+            for row in 0..lh.div_ceil(tile_size) {
+                for col in 0..lw.div_ceil(tile_size) {
+                    let x =
+                        (col * tile_size).saturating_sub(if col > 0 { self.overlap } else { 0 });
+                    let y =
+                        (row * tile_size).saturating_sub(if row > 0 { self.overlap } else { 0 });
+                    let right = ((col + 1) * tile_size + self.overlap).min(lw);
+                    let bottom = ((row + 1) * tile_size + self.overlap).min(lh);
+                    let tile = crop(&level_image, Region::Rect(x, y, right - x, bottom - y))?;
+                    let path = level_dir.join(format!("{col}_{row}.{}", self.format));
+                    write(&path, &self.format.process(tile)?)?;
+                }
+            }
+        }
+        write(
+            &dir.join(format!("{name}.dzi")),
+            self.descriptor(width, height).as_bytes(),
+        )
+    }
+}
+
+/// Zoomify 导出
+///
+/// Exports a Zoomify pyramid: `ImageProperties.xml` and `TileGroup{n}/{tier}-{col}-{row}.{format}`
+/// tiles, 256 tiles per group, counted from the smallest tier.
+///
+/// Example:
+/// ```
+/// use i3f::export::Zoomify;
+///
+/// let zoomify = Zoomify::default();
+/// assert_eq!(zoomify.tiers(300, 200), vec![(150, 100), (300, 200)]);
+/// assert_eq!(zoomify.tile_count(300, 200), 3);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zoomify {
+    /// 瓦片尺寸
+    pub tile_size: u32,
+
+    /// 瓦片格式
+    pub format: Format,
+}
+
+impl Default for Zoomify {
+    fn default() -> Self {
+        Self {
+            tile_size: 256,
+            format: Format::Jpg,
+        }
+    }
+}
+
+/// 每个 TileGroup 目录中的瓦片数
+const TILES_PER_GROUP: u32 = 256;
+
+impl Zoomify {
+    /// 各层级的尺寸，从最小层级到原图尺寸
+    pub fn tiers(&self, width: u32, height: u32) -> Vec<(u32, u32)> {
+        let tile_size = self.tile_size.max(1);
+        let mut tiers = vec![(width, height)];
+        let (mut w, mut h) = (width, height);
+        while w > tile_size || h > tile_size {
+            w = w.div_ceil(2);
+            h = h.div_ceil(2);
+            tiers.push((w, h));
+        }
+        tiers.reverse();
+        tiers
+    }
+
+    /// 瓦片总数
+    pub fn tile_count(&self, width: u32, height: u32) -> u32 {
+        self.tiers(width, height)
+            .iter()
+            .map(|&(w, h)| self.tier_tiles(w, h))
+            .sum()
+    }
+
+    fn tier_tiles(&self, width: u32, height: u32) -> u32 {
+        let tile_size = self.tile_size.max(1);
+        width.div_ceil(tile_size) * height.div_ceil(tile_size)
+    }
+
+    /// 生成 `ImageProperties.xml`
+    pub fn properties(&self, width: u32, height: u32) -> String {
+        format!(
+            "<IMAGE_PROPERTIES WIDTH=\"{width}\" HEIGHT=\"{height}\" NUMTILES=\"{}\" NUMIMAGES=\"1\" VERSION=\"1.8\" TILESIZE=\"{}\" />\n",
+            self.tile_count(width, height),
+            self.tile_size
+        )
+    }
+
+    /// 将图像导出到 `dir` 目录下
+    ///
+    /// Writes `ImageProperties.xml` and the `TileGroup` directories of `image` into `dir`.
+    pub fn export(&self, image: &DynamicImage, dir: &Path) -> Result<(), IiifError> {
+        let (width, height) = (image.width(), image.height());
+        let tile_size = self.tile_size.max(1);
+        let tiers = self.tiers(width, height);
+
+        // 先按从大到小的顺序生成各层级图像，再按从小到大的顺序编号
+        let mut images = Vec::with_capacity(tiers.len());
+        let mut tier_image = image.clone();
+        for &(w, h) in tiers.iter().rev() {
+            tier_image = resize(tier_image, w, h)?;
+            images.push(tier_image.clone());
+        }
+        images.reverse();
+
+        let mut index = 0;
+        for (tier, (tier_image, &(w, h))) in images.iter().zip(&tiers).enumerate() {
+            for row in 0..h.div_ceil(tile_size) {
+                for col in 0..w.div_ceil(tile_size) {
+                    let x = col * tile_size;
+                    let y = row * tile_size;
+                    let region = Region::Rect(x, y, tile_size.min(w - x), tile_size.min(h - y));
+                    let tile = crop(tier_image, region)?;
+                    let group_dir = dir.join(format!("TileGroup{}", index / TILES_PER_GROUP));
+                    create_dir(&group_dir)?;
+                    let path = group_dir.join(format!("{tier}-{col}-{row}.{}", self.format));
+                    write(&path, &self.format.process(tile)?)?;
+                    index += 1;
+                }
+            }
+        }
+        write(
+            &dir.join("ImageProperties.xml"),
+            self.properties(width, height).as_bytes(),
+        )
+    }
+}
+
+// 使用 IIIF 的 size 处理缩放到指定尺寸
+fn resize(image: DynamicImage, width: u32, height: u32) -> Result<DynamicImage, IiifError> {
+    let size = if (width, height) == (image.width(), image.height()) {
+        Size::Max
+    } else {
+        Size::WH {
+            w: width,
+            h: height,
+        }
+    };
+    size.process(image)
+}
+
+// 使用 IIIF 的 region 计算裁剪区域
+fn crop(image: &DynamicImage, region: Region) -> Result<DynamicImage, IiifError> {
+    let (x, y, w, h) = region.get_region(image.width(), image.height())?;
+    Ok(image.crop_imm(x, y, w, h))
+}
+
+fn create_dir(path: &Path) -> Result<(), IiifError> {
+    fs::create_dir_all(path).map_err(|e| IiifError::InternalServerError(e.to_string()))
+}
+
+fn write(path: &Path, data: &[u8]) -> Result<(), IiifError> {
+    fs::write(path, data).map_err(|e| IiifError::InternalServerError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use image::GenericImageView;
+
+    use crate::storage::{LocalStorage, Storage};
+
+    use super::*;
+
+    fn source() -> DynamicImage {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        image::load_from_memory(&storage.get_origin_file("demo.jpg").unwrap()).unwrap()
+    }
+
+    fn out_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("i3f-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_deep_zoom() {
+        let dir = out_dir("dzi");
+        let dzi = DeepZoom::default();
+        dzi.export(&source(), &dir, "demo").unwrap();
+
+        let descriptor = fs::read_to_string(dir.join("demo.dzi")).unwrap();
+        assert!(descriptor.contains("Overlap=\"1\" TileSize=\"254\">"));
+        assert!(descriptor.contains("<Size Width=\"300\" Height=\"200\"/>"));
+
+        let files = dir.join("demo_files");
+        assert_eq!(fs::read_dir(&files).unwrap().count(), 10);
+        let tile = image::open(files.join("9/1_0.jpg")).unwrap();
+        assert_eq!(tile.dimensions(), (47, 200));
+        let tile = image::open(files.join("9/0_0.jpg")).unwrap();
+        assert_eq!(tile.dimensions(), (255, 200));
+        let tile = image::open(files.join("0/0_0.jpg")).unwrap();
+        assert_eq!(tile.dimensions(), (1, 1));
+        assert!(!files.join("9/2_0.jpg").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_zoomify() {
+        let dir = out_dir("zoomify");
+        let zoomify = Zoomify {
+            tile_size: 64,
+            ..Default::default()
+        };
+        zoomify.export(&source(), &dir).unwrap();
+
+        // (38, 25) (75, 50) (150, 100) (300, 200)
+        assert_eq!(zoomify.tile_count(300, 200), 1 + 2 + 6 + 20);
+        let properties = fs::read_to_string(dir.join("ImageProperties.xml")).unwrap();
+        assert!(properties.contains("NUMTILES=\"29\""));
+        assert!(properties.contains("TILESIZE=\"64\""));
+
+        let group = dir.join("TileGroup0");
+        assert_eq!(fs::read_dir(&group).unwrap().count(), 29);
+        let tile = image::open(group.join("0-0-0.jpg")).unwrap();
+        assert_eq!(tile.dimensions(), (38, 25));
+        let tile = image::open(group.join("3-4-3.jpg")).unwrap();
+        assert_eq!(tile.dimensions(), (44, 8));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_zoomify_tile_groups() {
+        let zoomify = Zoomify::default();
+        // 4096x4096 共 1 + 4 + 16 + 64 + 256 = 341 个瓦片，需要两个 TileGroup
+        assert_eq!(zoomify.tile_count(4096, 4096), 341);
+        assert_eq!(zoomify.tiers(4096, 4096).len(), 5);
+    }
+}
@@ -0,0 +1,252 @@
+use std::fmt::Debug;
+
+use image::DynamicImage;
+
+use crate::IiifError;
+use crate::image::{Format, Quality, Rotation, ServiceConfig, resize_nearest};
+
+/// 图像处理后端，负责裁剪、缩放、旋转、色彩转换和编码
+///
+/// The image-processing backend used by the pipeline for crop, resize, rotate, colour
+/// conversion and encode. Every operation has a default implementation based on `image` and
+/// `imageproc`, so an alternative backend only overrides the operations it accelerates, for
+/// example a SIMD resize or a native-library encoder. The backend is selected at runtime
+/// through `ServiceConfig::backend`.
+///
+/// Backends are compared by [`Backend::name`], which is also part of the key used to merge
+/// identical concurrent requests.
+///
+/// Example:
+/// ```
+/// use std::sync::Arc;
+///
+/// use i3f::IiifError;
+/// use i3f::image::{Backend, ServiceConfig};
+/// use image::DynamicImage;
+/// use image::imageops::FilterType;
+///
+/// #[derive(Debug)]
+/// struct Smooth;
+///
+/// impl Backend for Smooth {
+///     fn name(&self) -> &str {
+///         "smooth"
+///     }
+///
+///     fn resize(&self, image: DynamicImage, width: u32, height: u32) -> Result<DynamicImage, IiifError> {
+///         Ok(image.resize_exact(width, height, FilterType::Lanczos3))
+///     }
+/// }
+///
+/// let config = ServiceConfig { backend: Arc::new(Smooth), ..Default::default() };
+/// assert_ne!(config, ServiceConfig::default());
+/// ```
+pub trait Backend: Debug + Send + Sync {
+    /// 后端名称
+    ///
+    /// The name of the backend.
+    fn name(&self) -> &str;
+
+    /// 裁剪出 `(x, y, width, height)` 区域，区域已确保在图像范围内
+    ///
+    /// Extracts the `width` x `height` region at `(x, y)`, already clamped to the image.
+    fn crop(
+        &self,
+        image: DynamicImage,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<DynamicImage, IiifError> {
+        Ok(image.crop_imm(x, y, width, height))
+    }
+
+    /// 缩放到精确的宽高
+    ///
+    /// Scales the image to exactly `width` x `height` pixels.
+    fn resize(
+        &self,
+        image: DynamicImage,
+        width: u32,
+        height: u32,
+    ) -> Result<DynamicImage, IiifError> {
+        Ok(resize_nearest(&image, width, height))
+    }
+
+    /// 镜像和旋转
+    ///
+    /// Mirrors and rotates the image.
+    fn rotate(&self, image: DynamicImage, rotation: &Rotation) -> Result<DynamicImage, IiifError> {
+        rotation.process(image)
+    }
+
+    /// 色彩转换：按画质转换为彩色、灰度或二值图像
+    ///
+    /// Converts the image to the colour, gray or bitonal quality.
+    fn convert(&self, image: DynamicImage, quality: &Quality) -> Result<DynamicImage, IiifError> {
+        quality.process(image)
+    }
+
+    /// 编码为输出格式并嵌入 ICC 配置文件
+    ///
+    /// Encodes the image with an embedded ICC profile, using the PDF and TIFF options of the
+    /// service.
+    fn encode(
+        &self,
+        image: DynamicImage,
+        format: &Format,
+        icc_profile: Option<&[u8]>,
+        config: &ServiceConfig,
+    ) -> Result<Vec<u8>, IiifError> {
+        format.encode_with(image, icc_profile, config)
+    }
+}
+
+impl PartialEq for dyn Backend + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+/// 基于 `image` 和 `imageproc` 的默认后端
+///
+/// The default backend, built on `image` and `imageproc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ImageBackend;
+
+impl Backend for ImageBackend {
+    fn name(&self) -> &str {
+        "image"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use image::imageops::FilterType;
+    use url::Url;
+
+    use super::*;
+    use crate::image::IiifImage;
+    use crate::storage::LocalStorage;
+
+    #[derive(Debug, Default)]
+    struct Recording {
+        calls: Mutex<Vec<&'static str>>,
+    }
+
+    impl Recording {
+        fn record(&self, call: &'static str) {
+            self.calls.lock().unwrap().push(call);
+        }
+    }
+
+    impl Backend for Recording {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        fn crop(
+            &self,
+            image: DynamicImage,
+            x: u32,
+            y: u32,
+            width: u32,
+            height: u32,
+        ) -> Result<DynamicImage, IiifError> {
+            self.record("crop");
+            ImageBackend.crop(image, x, y, width, height)
+        }
+
+        fn resize(
+            &self,
+            image: DynamicImage,
+            width: u32,
+            height: u32,
+        ) -> Result<DynamicImage, IiifError> {
+            self.record("resize");
+            ImageBackend.resize(image, width, height)
+        }
+
+        fn rotate(
+            &self,
+            image: DynamicImage,
+            rotation: &Rotation,
+        ) -> Result<DynamicImage, IiifError> {
+            self.record("rotate");
+            ImageBackend.rotate(image, rotation)
+        }
+
+        fn convert(
+            &self,
+            image: DynamicImage,
+            quality: &Quality,
+        ) -> Result<DynamicImage, IiifError> {
+            self.record("convert");
+            let mut image = ImageBackend.convert(image, quality)?;
+            image.invert();
+            Ok(image)
+        }
+
+        fn encode(
+            &self,
+            image: DynamicImage,
+            format: &Format,
+            icc_profile: Option<&[u8]>,
+            config: &ServiceConfig,
+        ) -> Result<Vec<u8>, IiifError> {
+            self.record("encode");
+            ImageBackend.encode(image, format, icc_profile, config)
+        }
+    }
+
+    #[test]
+    fn test_backend() {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let url = "https://example.org/image-service/demo.jpg/10,20,100,100/60,/90/gray.png";
+        let image = IiifImage::try_from(Url::parse(url).unwrap()).unwrap();
+
+        let backend = Arc::new(Recording::default());
+        let config = ServiceConfig {
+            backend: backend.clone(),
+            ..Default::default()
+        };
+        let result = image.process_with(&storage, &config).unwrap();
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/10,20,100,100/").unwrap();
+        let expected = ImageBackend
+            .convert(
+                image::open("./fixtures/demo.jpg")
+                    .unwrap()
+                    .crop_imm(10, 20, 100, 100)
+                    .resize_exact(60, 60, FilterType::Nearest)
+                    .rotate90(),
+                &Quality::Gray,
+            )
+            .unwrap();
+
+        assert_eq!(
+            *backend.calls.lock().unwrap(),
+            ["crop", "resize", "rotate", "convert", "encode"]
+        );
+        let output = image::load_from_memory(&result.data).unwrap().to_luma8();
+        let expected = expected.to_luma8();
+        assert_eq!(output.dimensions(), (60, 60));
+        assert_eq!(
+            output.get_pixel(0, 0).0[0],
+            255 - expected.get_pixel(0, 0).0[0]
+        );
+    }
+
+    #[test]
+    fn test_backend_eq() {
+        let config = ServiceConfig::default();
+        assert_eq!(config.backend.name(), "image");
+        assert_eq!(config, ServiceConfig::default());
+        let custom = ServiceConfig {
+            backend: Arc::new(Recording::default()),
+            ..Default::default()
+        };
+        assert_ne!(config, custom);
+    }
+}
@@ -0,0 +1,375 @@
+use crate::IiifError;
+use crate::image::{Format, IiifImage, ImageInfo, Quality, Region, Rotation, ServiceConfig, Size};
+
+/// 图像请求构建器，用于在客户端生成完整的图像 URL
+///
+/// A fluent builder for image requests. Starting from an `ImageInfo`, the request is
+/// validated against the advertised profile, extra features and size limits of the service.
+///
+/// Example:
+/// ```
+/// use i3f::image::{IiifImageBuilder, ImageInfo, Profile};
+///
+/// let info = ImageInfo {
+///     id: "https://example.org/iiif/demo.jpg".to_string(),
+///     profile: Profile::Level2,
+///     width: 300,
+///     height: 200,
+///     ..Default::default()
+/// };
+/// let url = IiifImageBuilder::from_info(&info)
+///     .region_pct(10.0, 10.0, 50.0, 50.0)
+///     .fit(100, 100)
+///     .rotate(90.0)
+///     .jpg()
+///     .url()
+///     .unwrap();
+/// assert_eq!(url, "https://example.org/iiif/demo.jpg/pct:10,10,50,50/!100,100/90/default.jpg");
+///
+/// // level2 不支持镜像
+/// assert!(IiifImageBuilder::from_info(&info).mirror().url().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct IiifImageBuilder {
+    base_uri: String,
+    config: Option<ServiceConfig>,
+    dimensions: Option<(u32, u32)>,
+    region: Region,
+    size: Size,
+    upscale: bool,
+    angle: f32,
+    mirror: bool,
+    quality: Quality,
+    format: Format,
+}
+
+impl IiifImageBuilder {
+    /// 从图像的基础 URI 开始构建，不进行服务能力校验
+    ///
+    /// Starts from the base URI of an image (scheme, server, prefix and identifier). No
+    /// capabilities are known, so only the syntax of the request is validated.
+    pub fn new(base_uri: &str) -> Self {
+        Self {
+            base_uri: base_uri.trim_end_matches('/').to_string(),
+            config: None,
+            dimensions: None,
+            region: Region::Full,
+            size: Size::Max,
+            upscale: false,
+            angle: 0.0,
+            mirror: false,
+            quality: Quality::Default,
+            format: Format::Jpg,
+        }
+    }
+
+    /// 从图像信息开始构建，按其声明的能力和尺寸限制进行校验
+    ///
+    /// Starts from an image information document, validating against its profile, extra
+    /// features, qualities, formats and size limits.
+    pub fn from_info(info: &ImageInfo) -> Self {
+        Self {
+            config: Some(ServiceConfig::from_info(info)),
+            dimensions: (info.width > 0 && info.height > 0).then_some((info.width, info.height)),
+            ..Self::new(&info.id)
+        }
+    }
+
+    /// 使用指定的服务能力进行校验
+    pub fn config(mut self, config: ServiceConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// 指定区域
+    pub fn region(mut self, region: Region) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// 完整图像
+    pub fn full(self) -> Self {
+        self.region(Region::Full)
+    }
+
+    /// 居中的正方形区域
+    pub fn square(self) -> Self {
+        self.region(Region::Square)
+    }
+
+    /// 按像素指定区域
+    pub fn region_px(self, x: u32, y: u32, w: u32, h: u32) -> Self {
+        self.region(Region::Rect(x, y, w, h))
+    }
+
+    /// 按百分比指定区域
+    pub fn region_pct(self, x: f32, y: f32, w: f32, h: f32) -> Self {
+        self.region(Region::Pct(x, y, w, h))
+    }
+
+    /// 指定尺寸
+    pub fn size(mut self, size: Size) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// 不缩放，或缩小到服务限制范围内
+    pub fn max(self) -> Self {
+        self.size(Size::Max)
+    }
+
+    /// 按宽度等比缩放
+    pub fn width(self, w: u32) -> Self {
+        self.size(Size::W { w })
+    }
+
+    /// 按高度等比缩放
+    pub fn height(self, h: u32) -> Self {
+        self.size(Size::H { h })
+    }
+
+    /// 缩放到精确的宽高
+    pub fn exact(self, w: u32, h: u32) -> Self {
+        self.size(Size::WH { w, h })
+    }
+
+    /// 等比缩放到不超过 `w` x `h`
+    pub fn fit(self, w: u32, h: u32) -> Self {
+        self.size(Size::LWH { w, h })
+    }
+
+    /// 按百分比缩放
+    pub fn pct(self, n: f32) -> Self {
+        self.size(Size::Pct { n })
+    }
+
+    /// 允许放大，即尺寸加上 `^` 前缀
+    pub fn upscale(mut self) -> Self {
+        self.upscale = true;
+        self
+    }
+
+    /// 顺时针旋转角度
+    pub fn rotate(mut self, angle: f32) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    /// 旋转前先进行镜像
+    pub fn mirror(mut self) -> Self {
+        self.mirror = true;
+        self
+    }
+
+    /// 指定画质
+    pub fn quality(mut self, quality: Quality) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    /// `color` 画质
+    pub fn color(self) -> Self {
+        self.quality(Quality::Color)
+    }
+
+    /// `gray` 画质
+    pub fn gray(self) -> Self {
+        self.quality(Quality::Gray)
+    }
+
+    /// `bitonal` 画质
+    pub fn bitonal(self) -> Self {
+        self.quality(Quality::Bitonal)
+    }
+
+    /// 指定格式
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// `jpg` 格式
+    pub fn jpg(self) -> Self {
+        self.format(Format::Jpg)
+    }
+
+    /// `png` 格式
+    pub fn png(self) -> Self {
+        self.format(Format::Png)
+    }
+
+    /// `webp` 格式
+    pub fn webp(self) -> Self {
+        self.format(Format::Webp)
+    }
+
+    /// `avif` 格式
+    #[cfg(feature = "avif")]
+    pub fn avif(self) -> Self {
+        self.format(Format::Avif)
+    }
+
+    /// 构建并校验请求
+    ///
+    /// Builds the request, failing with the `IiifError` the service would answer it with.
+    pub fn build(&self) -> Result<IiifImage, IiifError> {
+        let identifier = self
+            .base_uri
+            .rsplit('/')
+            .next()
+            .filter(|identifier| !identifier.is_empty())
+            .ok_or_else(|| IiifError::InvalidIiifURL("Identifier cannot be empty".to_string()))?;
+        let image = IiifImage {
+            identifier: super::url_decode(identifier)?,
+            region: self.region.clone(),
+            size: if self.upscale {
+                upscaled(&self.size)
+            } else {
+                self.size.clone()
+            },
+            rotation: if self.mirror {
+                Rotation::MirrorDegrees(self.angle)
+            } else {
+                Rotation::Degrees(self.angle)
+            },
+            quality: self.quality.clone(),
+            format: self.format.clone(),
+        };
+        // 通过字符串形式往返，保证生成的 URL 可以被服务端解析
+        let _: Region = image.region.to_string().parse()?;
+        let _: Size = image.size.to_string().parse()?;
+
+        let config = self.config.clone().unwrap_or_default();
+        if self.config.is_some() {
+            config.check(&image)?;
+        }
+        if let Some((width, height)) = self.dimensions {
+            image.plan(width, height, &config.limits)?;
+        } else {
+            image.rotation.get_size(1, 1)?;
+        }
+        Ok(image)
+    }
+
+    /// 构建并返回完整的图像 URL
+    ///
+    /// Builds the request and returns its full URL.
+    pub fn url(&self) -> Result<String, IiifError> {
+        let image = self.build()?;
+        Ok(format!(
+            "{}/{}/{}/{}/{}.{}",
+            self.base_uri, image.region, image.size, image.rotation, image.quality, image.format
+        ))
+    }
+}
+
+impl IiifImage {
+    /// 从图像的基础 URI 开始构建请求
+    ///
+    /// Returns a builder starting from the base URI of an image.
+    pub fn builder(base_uri: &str) -> IiifImageBuilder {
+        IiifImageBuilder::new(base_uri)
+    }
+}
+
+// 转换为允许放大的尺寸
+fn upscaled(size: &Size) -> Size {
+    match *size {
+        Size::Max => Size::CMax,
+        Size::W { w } => Size::CW { w },
+        Size::H { h } => Size::CH { h },
+        Size::Pct { n } => Size::CPct { n },
+        Size::WH { w, h } => Size::CWH { w, h },
+        Size::LWH { w, h } => Size::CLWH { w, h },
+        ref size => size.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::{Feature, Limits, Profile};
+
+    use super::*;
+
+    fn info(profile: Profile) -> ImageInfo {
+        ImageInfo {
+            id: "https://example.org/iiif/ark:%2F12025%2F654/".to_string(),
+            profile,
+            width: 300,
+            height: 200,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_builder_url() {
+        let url = IiifImage::builder("https://example.org/iiif/demo.jpg")
+            .square()
+            .width(150)
+            .upscale()
+            .rotate(22.5)
+            .mirror()
+            .gray()
+            .png()
+            .url()
+            .unwrap();
+        assert_eq!(
+            url,
+            "https://example.org/iiif/demo.jpg/square/^150,/!22.5/gray.png"
+        );
+
+        let image = IiifImage::builder("https://example.org/iiif/demo.jpg")
+            .build()
+            .unwrap();
+        assert_eq!(image.to_string(), "demo.jpg/full/max/0/default.jpg");
+    }
+
+    #[test]
+    fn test_builder_from_info() {
+        let builder = IiifImageBuilder::from_info(&info(Profile::Level1));
+        let image = builder.clone().region_px(0, 0, 100, 100).build().unwrap();
+        assert_eq!(image.identifier, "ark:/12025/654");
+        assert_eq!(
+            builder.clone().height(100).url().unwrap(),
+            "https://example.org/iiif/ark:%2F12025%2F654/full/,100/0/default.jpg"
+        );
+
+        for builder in [
+            builder.clone().region_pct(0.0, 0.0, 50.0, 50.0),
+            builder.clone().fit(100, 100),
+            builder.clone().rotate(90.0),
+            builder.clone().gray(),
+            builder.clone().png(),
+            builder.clone().width(400),
+        ] {
+            assert!(builder.build().is_err());
+        }
+    }
+
+    #[test]
+    fn test_builder_extras_and_limits() {
+        let info = ImageInfo {
+            extra_features: Some(vec![Feature::SizeUpscaling]),
+            max_width: Some(400),
+            ..info(Profile::Level2)
+        };
+        let builder = IiifImageBuilder::from_info(&info);
+        assert!(builder.clone().width(400).upscale().build().is_ok());
+        assert!(matches!(
+            builder.clone().width(500).upscale().build(),
+            Err(IiifError::NotFound(_))
+        ));
+
+        let builder =
+            IiifImage::builder("https://example.org/iiif/demo.jpg").config(ServiceConfig {
+                limits: Limits {
+                    max_width: Some(100),
+                    ..Default::default()
+                },
+                ..ServiceConfig::level0()
+            });
+        assert!(builder.clone().build().is_ok());
+        assert!(builder.clone().rotate(90.0).build().is_err());
+        assert!(builder.rotate(400.0).build().is_err());
+    }
+}
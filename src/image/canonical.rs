@@ -0,0 +1,172 @@
+use crate::IiifError;
+use crate::image::{IiifImage, ImagePlan, Limits, Region, Rotation, Size};
+
+impl IiifImage {
+    /// 根据原图尺寸生成规范（canonical）形式的请求
+    ///
+    /// Returns the canonical form of the request for a source image of `width` x `height`
+    /// pixels, as defined in [Canonical URI Syntax](https://iiif.io/api/image/3.0/#47-canonical-uri-syntax):
+    ///
+    /// - region: `full` if the whole image is requested, otherwise `x,y,w,h`
+    /// - size: `max` if the extracted region is returned unscaled, otherwise `w,h` (`^w,h` when upscaled)
+    /// - rotation: `!` if mirrored, followed by the angle in `[0, 360)`
+    /// - quality and format are kept as requested
+    ///
+    /// Requests that produce the same image share the same canonical form, e.g. `pct:0,0,100,100`
+    /// and `full`.
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::image::IiifImage;
+    /// use url::Url;
+    ///
+    /// let url = Url::parse("https://example.org/iiif/demo.jpg/pct:0,0,100,100/!150,150/360/default.jpg").unwrap();
+    /// let image = IiifImage::try_from(url).unwrap();
+    /// let canonical = image.canonical(300, 200).unwrap();
+    /// assert_eq!(canonical.to_string(), "demo.jpg/full/150,100/0/default.jpg");
+    /// ```
+    pub fn canonical(&self, width: u32, height: u32) -> Result<IiifImage, IiifError> {
+        let plan = self.plan(width, height, &Limits::default())?;
+        Ok(self.canonical_for(&plan))
+    }
+
+    /// 根据已计算的处理计划生成规范形式的请求
+    ///
+    /// Returns the canonical form of the request for an already resolved `plan`.
+    pub fn canonical_for(&self, plan: &ImagePlan) -> IiifImage {
+        let region = if plan.is_full_region() {
+            Region::Full
+        } else {
+            let crop = plan.crop;
+            Region::Rect(crop.x, crop.y, crop.width, crop.height)
+        };
+
+        let (sw, sh) = (plan.scaled_width, plan.scaled_height);
+        let size = if !plan.needs_resize() {
+            Size::Max
+        } else if sw > plan.crop.width || sh > plan.crop.height {
+            Size::CWH { w: sw, h: sh }
+        } else {
+            Size::WH { w: sw, h: sh }
+        };
+
+        let rotation = match self.rotation {
+            Rotation::Degrees(angle) => Rotation::Degrees(angle % 360.0),
+            Rotation::MirrorDegrees(angle) => Rotation::MirrorDegrees(angle % 360.0),
+        };
+
+        IiifImage {
+            identifier: self.identifier.clone(),
+            region,
+            size,
+            rotation,
+            quality: self.quality.clone(),
+            format: self.format.clone(),
+        }
+    }
+
+    /// 生成规范形式的完整 URL
+    ///
+    /// Returns the canonical URL of the request, `base_uri` being the scheme, server and
+    /// prefix of the image service (without a trailing slash).
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::image::IiifImage;
+    /// use url::Url;
+    ///
+    /// let url = Url::parse("https://example.org/iiif/demo.jpg/square/max/0/default.jpg").unwrap();
+    /// let image = IiifImage::try_from(url).unwrap();
+    /// let canonical = image.canonical_url("https://example.org/iiif", 300, 200).unwrap();
+    /// assert_eq!(canonical, "https://example.org/iiif/demo.jpg/50,0,200,200/max/0/default.jpg");
+    /// ```
+    pub fn canonical_url(
+        &self,
+        base_uri: &str,
+        width: u32,
+        height: u32,
+    ) -> Result<String, IiifError> {
+        let canonical = self.canonical(width, height)?;
+        Ok(format!("{}/{}", base_uri.trim_end_matches('/'), canonical))
+    }
+
+    /// 生成 `CanonicalLinkHeader` 功能所需的 HTTP `Link` 头的值
+    ///
+    /// Returns the value of the HTTP `Link` header advertised by the `canonicalLinkHeader` feature.
+    pub fn canonical_link_header(
+        &self,
+        base_uri: &str,
+        width: u32,
+        height: u32,
+    ) -> Result<String, IiifError> {
+        let url = self.canonical_url(base_uri, width, height)?;
+        Ok(format!("<{url}>;rel=\"canonical\""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::*;
+
+    #[test]
+    fn test_canonical() {
+        let cases = vec![
+            ("full/max/0/default.jpg", "full/max/0/default.jpg"),
+            (
+                "pct:0,0,100,100/max/0/default.jpg",
+                "full/max/0/default.jpg",
+            ),
+            ("0,0,300,200/max/0/default.jpg", "full/max/0/default.jpg"),
+            ("0,0,500,500/max/0/default.jpg", "full/max/0/default.jpg"),
+            ("square/max/0/default.jpg", "50,0,200,200/max/0/default.jpg"),
+            (
+                "pct:41.6,7.5,40,70/max/0/gray.png",
+                "125,15,120,140/max/0/gray.png",
+            ),
+            ("full/300,/0/default.jpg", "full/max/0/default.jpg"),
+            ("full/150,/0/default.jpg", "full/150,100/0/default.jpg"),
+            ("full/,100/0/default.jpg", "full/150,100/0/default.jpg"),
+            ("full/pct:50/0/default.jpg", "full/150,100/0/default.jpg"),
+            ("full/!150,150/0/default.jpg", "full/150,100/0/default.jpg"),
+            ("full/^360,/0/default.jpg", "full/^360,240/0/default.jpg"),
+            ("full/^max/0/default.jpg", "full/max/0/default.jpg"),
+            ("full/max/360/default.jpg", "full/max/0/default.jpg"),
+            ("full/max/!22.50/default.jpg", "full/max/!22.5/default.jpg"),
+        ];
+        for (request, expected) in cases {
+            let url = Url::parse(&format!("https://example.org/iiif/demo.jpg/{request}")).unwrap();
+            let image = IiifImage::try_from(url).unwrap();
+            let canonical = image.canonical(300, 200).unwrap();
+            assert_eq!(canonical.to_string(), format!("demo.jpg/{expected}"));
+            // 规范形式是幂等的
+            assert_eq!(canonical.canonical(300, 200).unwrap(), canonical);
+        }
+    }
+
+    #[test]
+    fn test_canonical_error() {
+        let url = Url::parse("https://example.org/iiif/demo.jpg/full/500,/0/default.jpg").unwrap();
+        let image = IiifImage::try_from(url).unwrap();
+        assert!(image.canonical(300, 200).is_err());
+
+        let url =
+            Url::parse("https://example.org/iiif/demo.jpg/300,0,10,10/max/0/default.jpg").unwrap();
+        let image = IiifImage::try_from(url).unwrap();
+        assert!(image.canonical(300, 200).is_err());
+    }
+
+    #[test]
+    fn test_canonical_link_header() {
+        let url = Url::parse("https://example.org/iiif/ark:%2F12025%2F654/full/150,/0/default.jpg")
+            .unwrap();
+        let image = IiifImage::try_from(url).unwrap();
+        assert_eq!(
+            image
+                .canonical_link_header("https://example.org/iiif/", 300, 200)
+                .unwrap(),
+            "<https://example.org/iiif/ark%3A%2F12025%2F654/full/150,100/0/default.jpg>;rel=\"canonical\""
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use image::{DynamicImage, GenericImageView, ImageBuffer};
+use moxcms::{ColorProfile, DataColorSpace, Layout, ToneReprCurve, TransformOptions};
+
+/// 色彩管理策略，决定如何处理原图中嵌入的 ICC 色彩配置文件
+///
+/// How embedded ICC color profiles of sources are handled. Sources without a profile are
+/// assumed to be sRGB and left untouched by both strategies.
+///
+/// Example:
+/// ```
+/// use i3f::image::{ColorManagement, Source};
+///
+/// let data = std::fs::read("./fixtures/adobe-rgb.jpg").unwrap();
+/// let source = Source::new(&data, true).unwrap();
+/// let image = source.decode().unwrap();
+///
+/// let (_, profile) = ColorManagement::Convert.process(image.clone(), source.icc_profile());
+/// assert!(profile.is_none());
+///
+/// let (_, profile) = ColorManagement::Preserve.process(image, source.icc_profile());
+/// assert_eq!(profile.as_deref(), source.icc_profile());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorManagement {
+    /// 将像素转换到 sRGB，灰度图像转换为使用 sRGB 色调曲线的灰度，输出不嵌入配置文件
+    ///
+    /// Converts pixels to sRGB, outputs carry no profile. Gray sources are converted to gray
+    /// with the sRGB tone curve and stay single-channel.
+    #[default]
+    Convert,
+
+    /// 保留像素值，并在 JPEG/PNG/TIFF/WebP/PDF 输出中嵌入原图的 RGB 或灰度配置文件。
+    /// 嵌入灰度配置文件的输出保持灰度，WebP 只能存储 RGB，灰度配置文件仍转换为 sRGB
+    ///
+    /// Keeps pixel values and embeds the source RGB or gray profile in JPEG, PNG, TIFF, WebP
+    /// and PDF outputs. Outputs embedding a gray profile stay gray; WebP only stores RGB, so
+    /// gray profiles are still converted for WebP outputs.
+    Preserve,
+}
+
+impl ColorManagement {
+    /// 处理解码后的图像，返回处理后的图像以及需要嵌入输出的配置文件
+    ///
+    /// Applies the strategy to a decoded image with its embedded profile. Returns the image
+    /// and the profile to embed in the output, if any. Profiles equivalent to sRGB are not
+    /// transformed. Profiles that cannot be parsed or describe other color spaces (CMYK,
+    /// Lab, ...) are dropped with a warning.
+    pub fn process(
+        &self,
+        image: DynamicImage,
+        icc_profile: Option<&[u8]>,
+    ) -> (DynamicImage, Option<Vec<u8>>) {
+        let Some(icc_profile) = icc_profile else {
+            return (image, None);
+        };
+        let profile = match ColorProfile::new_from_slice(icc_profile) {
+            Ok(profile) => profile,
+            Err(e) => {
+                tracing::warn!(error = %e, "ignoring invalid ICC profile");
+                return (image, None);
+            }
+        };
+        match profile.color_space {
+            DataColorSpace::Rgb | DataColorSpace::Gray if *self == ColorManagement::Preserve => {
+                (image, Some(icc_profile.to_vec()))
+            }
+            DataColorSpace::Rgb if is_srgb(&profile) => (image, None),
+            DataColorSpace::Rgb | DataColorSpace::Gray => match to_srgb(&image, &profile) {
+                Ok(image) => (image, None),
+                Err(e) => {
+                    tracing::warn!(error = %e, "failed to convert ICC profile to sRGB");
+                    (image, None)
+                }
+            },
+            color_space => {
+                tracing::warn!(?color_space, "ignoring unsupported ICC profile");
+                (image, None)
+            }
+        }
+    }
+}
+
+/// 每个通道是否超过 8 位
+///
+/// Whether the image has more than 8 bits per channel (16-bit or floating point).
+pub(crate) fn is_high_bit_depth(image: &DynamicImage) -> bool {
+    let color = image.color();
+    color.bits_per_pixel() / color.channel_count() as u16 > 8
+}
+
+/// ICC 配置文件头中的色彩空间是否为灰度
+///
+/// Whether the data color space in the header of an ICC profile is gray.
+pub(crate) fn is_gray_profile(icc_profile: &[u8]) -> bool {
+    icc_profile.get(16..20) == Some(b"GRAY")
+}
+
+/// 输出是否保持灰度：嵌入配置文件时由配置文件的色彩空间决定，否则由图像是否含有彩色决定
+///
+/// Whether an output stays gray. Outputs embedding a profile follow its color space, others
+/// stay gray if the image has no color.
+pub(crate) fn keeps_gray(image: &DynamicImage, icc_profile: Option<&[u8]>) -> bool {
+    match icc_profile {
+        Some(icc_profile) => is_gray_profile(icc_profile),
+        None => !image.color().has_color(),
+    }
+}
+
+/// 配置文件是否与 sRGB 等效：原色相同且三条色调曲线都与 sRGB 曲线一致
+fn is_srgb(profile: &ColorProfile) -> bool {
+    let srgb = ColorProfile::new_srgb();
+    if !profile.is_matrix_shaper() {
+        return false;
+    }
+    let colorants = profile.colorant_matrix().v.into_iter().flatten();
+    let reference = srgb.colorant_matrix().v.into_iter().flatten();
+    if colorants.zip(reference).any(|(a, b)| (a - b).abs() > 2e-3) {
+        return false;
+    }
+    let Some(Ok(reference)) = srgb
+        .red_trc
+        .as_ref()
+        .map(ToneReprCurve::make_linear_evaluator)
+    else {
+        return false;
+    };
+    [&profile.red_trc, &profile.green_trc, &profile.blue_trc]
+        .into_iter()
+        .all(
+            |trc| match trc.as_ref().map(ToneReprCurve::make_linear_evaluator) {
+                Some(Ok(trc)) => (0..=64).all(|i| {
+                    let value = i as f32 / 64.0;
+                    (trc.evaluate_value(value) - reference.evaluate_value(value)).abs() < 2e-3
+                }),
+                _ => false,
+            },
+        )
+}
+
+/// 使用配置文件将图像转换到 sRGB，灰度图像转换到使用 sRGB 色调曲线的灰度，16 位图像保持 16 位精度
+fn to_srgb(image: &DynamicImage, profile: &ColorProfile) -> Result<DynamicImage, moxcms::CmsError> {
+    let srgb = ColorProfile::new_srgb();
+    let gray = profile.color_space == DataColorSpace::Gray;
+    let target = if gray {
+        let mut target = ColorProfile::new_gray_with_gamma(1.0);
+        target.gray_trc = srgb.red_trc.clone();
+        target
+    } else {
+        srgb
+    };
+    let options = TransformOptions::default();
+    let (width, height) = image.dimensions();
+    let layout = match (gray, image.color().has_alpha()) {
+        (true, true) => Layout::GrayAlpha,
+        (true, false) => Layout::Gray,
+        (false, true) => Layout::Rgba,
+        (false, false) => Layout::Rgb,
+    };
+
+    if is_high_bit_depth(image) {
+        let src = match layout {
+            Layout::GrayAlpha => image.to_luma_alpha16().into_raw(),
+            Layout::Gray => image.to_luma16().into_raw(),
+            Layout::Rgba => image.to_rgba16().into_raw(),
+            _ => image.to_rgb16().into_raw(),
+        };
+        let mut dst = vec![0u16; src.len()];
+        profile
+            .create_transform_16bit(layout, &target, layout, options)?
+            .transform(&src, &mut dst)?;
+        Ok(match layout {
+            Layout::GrayAlpha => {
+                DynamicImage::ImageLumaA16(ImageBuffer::from_raw(width, height, dst).unwrap())
+            }
+            Layout::Gray => {
+                DynamicImage::ImageLuma16(ImageBuffer::from_raw(width, height, dst).unwrap())
+            }
+            Layout::Rgba => {
+                DynamicImage::ImageRgba16(ImageBuffer::from_raw(width, height, dst).unwrap())
+            }
+            _ => DynamicImage::ImageRgb16(ImageBuffer::from_raw(width, height, dst).unwrap()),
+        })
+    } else {
+        let src = match layout {
+            Layout::GrayAlpha => image.to_luma_alpha8().into_raw(),
+            Layout::Gray => image.to_luma8().into_raw(),
+            Layout::Rgba => image.to_rgba8().into_raw(),
+            _ => image.to_rgb8().into_raw(),
+        };
+        let mut dst = vec![0u8; src.len()];
+        profile
+            .create_transform_8bit(layout, &target, layout, options)?
+            .transform(&src, &mut dst)?;
+        Ok(match layout {
+            Layout::GrayAlpha => {
+                DynamicImage::ImageLumaA8(ImageBuffer::from_raw(width, height, dst).unwrap())
+            }
+            Layout::Gray => {
+                DynamicImage::ImageLuma8(ImageBuffer::from_raw(width, height, dst).unwrap())
+            }
+            Layout::Rgba => {
+                DynamicImage::ImageRgba8(ImageBuffer::from_raw(width, height, dst).unwrap())
+            }
+            _ => DynamicImage::ImageRgb8(ImageBuffer::from_raw(width, height, dst).unwrap()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn test_convert_adobe_rgb() {
+        let icc = ColorProfile::new_adobe_rgb().encode().unwrap();
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(2, 2, Rgb([128, 64, 32])));
+        let (converted, profile) = ColorManagement::Convert.process(image.clone(), Some(&icc));
+        assert!(profile.is_none());
+        let pixel = converted.to_rgb8().get_pixel(0, 0).0;
+        assert_ne!(pixel, [128, 64, 32]);
+        // Adobe RGB 色域更广，相同数值在 sRGB 中饱和度更高
+        assert!(pixel[0] > 128 && pixel[1] < 64);
+
+        let (preserved, profile) = ColorManagement::Preserve.process(image.clone(), Some(&icc));
+        assert_eq!(preserved, image);
+        assert_eq!(profile, Some(icc));
+    }
+
+    #[test]
+    fn test_convert_16bit_and_gray() {
+        let icc = ColorProfile::new_pro_photo_rgb().encode().unwrap();
+        let image =
+            DynamicImage::ImageRgb16(ImageBuffer::from_pixel(1, 1, Rgb([30000, 20000, 10000])));
+        let (converted, _) = ColorManagement::Convert.process(image, Some(&icc));
+        assert!(matches!(converted, DynamicImage::ImageRgb16(_)));
+
+        // 灰度配置文件转换为 sRGB 色调曲线的灰度，保持单通道
+        let icc = ColorProfile::new_gray_with_gamma(1.0).encode().unwrap();
+        let image = DynamicImage::ImageLuma8(ImageBuffer::from_pixel(1, 1, image::Luma([128])));
+        let (converted, profile) = ColorManagement::Convert.process(image.clone(), Some(&icc));
+        assert!(profile.is_none());
+        let DynamicImage::ImageLuma8(converted) = converted else {
+            panic!("gray image converted to {:?}", converted.color());
+        };
+        assert!(converted.get_pixel(0, 0).0[0] > 128);
+
+        // 选择保留时灰度配置文件与像素一并保留
+        let (preserved, profile) = ColorManagement::Preserve.process(image.clone(), Some(&icc));
+        assert_eq!(preserved, image);
+        assert_eq!(profile, Some(icc));
+    }
+
+    #[test]
+    fn test_skip_srgb() {
+        let icc = ColorProfile::new_srgb().encode().unwrap();
+        let image = DynamicImage::ImageRgb8(RgbImage::from_fn(16, 16, |x, y| {
+            Rgb([x as u8 * 16, y as u8 * 16, 200])
+        }));
+        let (output, profile) = ColorManagement::Convert.process(image.clone(), Some(&icc));
+        assert_eq!(output, image);
+        assert!(profile.is_none());
+
+        assert!(is_srgb(&ColorProfile::new_srgb()));
+        assert!(!is_srgb(&ColorProfile::new_adobe_rgb()));
+        assert!(!is_srgb(&ColorProfile::new_display_p3()));
+    }
+
+    #[test]
+    fn test_keeps_gray() {
+        let gray = ColorProfile::new_gray_with_gamma(2.2).encode().unwrap();
+        let rgb = ColorProfile::new_srgb().encode().unwrap();
+        let luma = DynamicImage::ImageLuma8(image::GrayImage::new(1, 1));
+        let color = DynamicImage::ImageRgb8(RgbImage::new(1, 1));
+        assert!(is_gray_profile(&gray));
+        assert!(!is_gray_profile(&rgb));
+        assert!(keeps_gray(&luma, None));
+        assert!(!keeps_gray(&color, None));
+        assert!(keeps_gray(&color, Some(&gray)));
+        assert!(!keeps_gray(&luma, Some(&rgb)));
+    }
+
+    #[test]
+    fn test_invalid_profile() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(1, 1));
+        let (output, profile) = ColorManagement::Preserve.process(image.clone(), Some(b"junk"));
+        assert_eq!(output, image);
+        assert!(profile.is_none());
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Condvar, Mutex},
+};
+
+/// 单次执行（single-flight）合并器
+///
+/// Coalesces concurrent calls that share the same key: the first caller runs the
+/// computation, every other caller blocks until it finishes and receives a clone
+/// of the same result.
+pub(crate) struct SingleFlight<T> {
+    calls: Mutex<HashMap<String, Arc<Call<T>>>>,
+}
+
+/// 一次正在进行中的调用
+struct Call<T> {
+    state: Mutex<CallState<T>>,
+    done: Condvar,
+}
+
+enum CallState<T> {
+    /// 计算尚未完成
+    Pending,
+    /// 计算已完成
+    Done(T),
+    /// 执行者在完成前 panic，等待者需要重新发起调用
+    Abandoned,
+}
+
+/// 执行者的守卫，确保无论是否 panic 都会唤醒等待者并移除调用记录
+struct Leader<'a, T> {
+    flight: &'a SingleFlight<T>,
+    key: &'a str,
+    call: Arc<Call<T>>,
+    result: Option<T>,
+}
+
+impl<T> Drop for Leader<'_, T> {
+    fn drop(&mut self) {
+        if let Ok(mut calls) = self.flight.calls.lock() {
+            calls.remove(self.key);
+        }
+        let mut state = self
+            .call
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *state = match self.result.take() {
+            Some(result) => CallState::Done(result),
+            None => CallState::Abandoned,
+        };
+        self.call.done.notify_all();
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 执行 `f`，相同 `key` 的并发调用只会执行一次
+    ///
+    /// Runs `f` unless an identical call is already in flight, in which case the
+    /// result of that call is awaited and returned instead.
+    pub(crate) fn run<F: FnOnce() -> T>(&self, key: &str, f: F) -> T {
+        loop {
+            let (call, is_leader) = {
+                let mut calls = self
+                    .calls
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                match calls.get(key) {
+                    Some(call) => (call.clone(), false),
+                    None => {
+                        let call = Arc::new(Call {
+                            state: Mutex::new(CallState::Pending),
+                            done: Condvar::new(),
+                        });
+                        calls.insert(key.to_string(), call.clone());
+                        (call, true)
+                    }
+                }
+            };
+
+            if is_leader {
+                let mut leader = Leader {
+                    flight: self,
+                    key,
+                    call,
+                    result: None,
+                };
+                let result = f();
+                leader.result = Some(result.clone());
+                return result;
+            }
+
+            let mut state = call
+                .state
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            while let CallState::Pending = *state {
+                state = call
+                    .done
+                    .wait(state)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+            if let CallState::Done(result) = &*state {
+                return result.clone();
+            }
+            // 执行者异常退出，重新尝试
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Barrier,
+            atomic::{AtomicUsize, Ordering},
+        },
+        thread,
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_single_flight_coalesces() {
+        let flight = SingleFlight::new();
+        let counter = AtomicUsize::new(0);
+        let barrier = Barrier::new(8);
+
+        let results: Vec<usize> = thread::scope(|s| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    s.spawn(|| {
+                        barrier.wait();
+                        flight.run("demo.jpg/full/max/0/default.jpg", || {
+                            thread::sleep(Duration::from_millis(100));
+                            counter.fetch_add(1, Ordering::SeqCst) + 1
+                        })
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        assert!(results.iter().all(|r| *r == 1));
+        assert!(flight.calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_single_flight_distinct_keys() {
+        let flight = SingleFlight::new();
+        assert_eq!(flight.run("a", || 1), 1);
+        assert_eq!(flight.run("b", || 2), 2);
+        // 调用完成后不再缓存结果
+        assert_eq!(flight.run("a", || 3), 3);
+    }
+
+    #[test]
+    fn test_single_flight_leader_panic() {
+        let flight = SingleFlight::<u32>::new();
+        let result = thread::scope(|s| s.spawn(|| flight.run("a", || panic!("boom"))).join());
+        assert!(result.is_err());
+        assert_eq!(flight.run("a", || 7), 7);
+    }
+}
@@ -2,17 +2,21 @@ use std::{fmt::Display, str::FromStr};
 
 use image::DynamicImage;
 use image::ImageEncoder;
+#[cfg(feature = "avif")]
+use image::codecs::avif::AvifEncoder;
+#[cfg(feature = "bmp")]
+use image::codecs::bmp::BmpEncoder;
 use image::codecs::gif::GifEncoder;
 use image::codecs::jpeg::JpegEncoder;
 use image::codecs::png::PngEncoder;
 use image::codecs::tiff::TiffEncoder;
 use image::codecs::webp::WebPEncoder;
-use lopdf::{Document, Object, Stream, dictionary};
 use serde::Deserialize;
 use serde::Serialize;
 use std::io::Cursor;
 
 use crate::IiifError;
+use crate::image::{ColorManagement, ServiceConfig, is_gray_profile, is_high_bit_depth};
 
 /// Format 格式定义
 ///
@@ -26,7 +30,7 @@ use crate::IiifError;
 /// let format_png: Format = "png".parse().unwrap();
 /// println!("{:?}", format_png);
 /// ```
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Format {
     /// Format: `jpg`
@@ -77,6 +81,22 @@ pub enum Format {
     ///
     /// 图像将以 WebP 格式返回。
     Webp,
+
+    /// Format: `avif`
+    ///
+    /// The image is returned in AVIF format. An extension format, advertised through
+    /// `extraFormats` and `preferredFormats`. Encoding requires the `avif` feature.
+    ///
+    /// 图像将以 AVIF 格式返回，扩展格式，编码需要启用 `avif` feature。
+    Avif,
+
+    /// Format: `bmp`
+    ///
+    /// The image is returned in BMP format. An extension format, advertised through
+    /// `extraFormats`. Encoding requires the `bmp` feature.
+    ///
+    /// 图像将以 BMP 格式返回，扩展格式，编码需要启用 `bmp` feature。
+    Bmp,
 }
 
 impl FromStr for Format {
@@ -96,6 +116,8 @@ impl FromStr for Format {
             "jp2" => Ok(Format::Jp2),
             "pdf" => Ok(Format::Pdf),
             "webp" => Ok(Format::Webp),
+            "avif" => Ok(Format::Avif),
+            "bmp" => Ok(Format::Bmp),
             _ => Err(IiifError::BadRequest("Invalid file format".to_string())),
         }
     }
@@ -111,6 +133,8 @@ impl Display for Format {
             Format::Jp2 => write!(f, "jp2"),
             Format::Pdf => write!(f, "pdf"),
             Format::Webp => write!(f, "webp"),
+            Format::Avif => write!(f, "avif"),
+            Format::Bmp => write!(f, "bmp"),
         }
     }
 }
@@ -125,32 +149,77 @@ impl Format {
             Self::Tif => "image/tiff",
             Self::Jp2 => "image/jp2",
             Self::Pdf => "application/pdf",
+            Self::Avif => "image/avif",
+            Self::Bmp => "image/bmp",
         }
     }
 
     pub fn process(&self, image: DynamicImage) -> Result<Vec<u8>, IiifError> {
+        self.encode(image, None)
+    }
+
+    /// 编码图像并嵌入 ICC 配置文件，GIF、AVIF 和 BMP 不支持嵌入配置文件时将其忽略
+    ///
+    /// Encodes the image with an embedded ICC profile. GIF, AVIF and BMP outputs cannot carry
+    /// a profile, which is then ignored.
+    pub fn encode(
+        &self,
+        image: DynamicImage,
+        icc_profile: Option<&[u8]>,
+    ) -> Result<Vec<u8>, IiifError> {
+        self.encode_with(image, icc_profile, &ServiceConfig::default())
+    }
+
+    /// 与 [`Format::encode`] 相同，按服务配置中的 PDF 和 TIFF 输出选项编码
+    ///
+    /// Same as [`Format::encode`], with the PDF options (`ServiceConfig::pdf`) and the tiled
+    /// TIFF layout (`ServiceConfig::tiff`) of the service.
+    pub fn encode_with(
+        &self,
+        image: DynamicImage,
+        icc_profile: Option<&[u8]>,
+        config: &ServiceConfig,
+    ) -> Result<Vec<u8>, IiifError> {
         let mut bytes = Vec::new();
 
         match self {
             Format::Jpg => {
-                let rgb = image.to_rgb8();
+                // 嵌入灰度配置文件时输出灰度 JPEG
+                let (samples, color_type) = if icc_profile.is_some_and(is_gray_profile) {
+                    (image.to_luma8().into_raw(), image::ExtendedColorType::L8)
+                } else {
+                    (image.to_rgb8().into_raw(), image::ExtendedColorType::Rgb8)
+                };
                 let mut cursor = Cursor::new(&mut bytes);
-                let encoder = JpegEncoder::new(&mut cursor);
+                let mut encoder = JpegEncoder::new(&mut cursor);
+                set_icc_profile(&mut encoder, icc_profile)?;
                 encoder
-                    .write_image(
-                        rgb.as_raw(),
-                        rgb.width(),
-                        rgb.height(),
-                        image::ExtendedColorType::Rgb8,
-                    )
+                    .write_image(&samples, image.width(), image.height(), color_type)
                     .map_err(|e| {
                         IiifError::InternalServerError(format!("Failed to encode JPEG image: {e}"))
                     })?;
             }
             Format::Png => {
+                // 16 位图像保持 16 位输出
+                let image = lossless_layout(image, icc_profile, true);
+                let mut cursor = Cursor::new(&mut bytes);
+                let mut encoder = PngEncoder::new(&mut cursor);
+                set_icc_profile(&mut encoder, icc_profile)?;
+                image.write_with_encoder(encoder).map_err(|e| {
+                    IiifError::InternalServerError(format!("Failed to encode PNG image: {e}"))
+                })?;
+            }
+            Format::Webp => {
+                // WebP 只能存储 RGB，灰度配置文件转换后不嵌入
+                let (image, icc_profile) = if icc_profile.is_some_and(is_gray_profile) {
+                    (ColorManagement::Convert.process(image, icc_profile).0, None)
+                } else {
+                    (image, icc_profile)
+                };
                 let rgba = image.to_rgba8();
                 let mut cursor = Cursor::new(&mut bytes);
-                let encoder = PngEncoder::new(&mut cursor);
+                let mut encoder = WebPEncoder::new_lossless(&mut cursor);
+                set_icc_profile(&mut encoder, icc_profile)?;
                 encoder
                     .write_image(
                         rgba.as_raw(),
@@ -159,13 +228,13 @@ impl Format {
                         image::ExtendedColorType::Rgba8,
                     )
                     .map_err(|e| {
-                        IiifError::InternalServerError(format!("Failed to encode PNG image: {e}"))
+                        IiifError::InternalServerError(format!("Failed to encode WebP image: {e}"))
                     })?;
             }
-            Format::Webp => {
+            Format::Gif => {
                 let rgba = image.to_rgba8();
                 let mut cursor = Cursor::new(&mut bytes);
-                let encoder = WebPEncoder::new_lossless(&mut cursor);
+                let encoder = GifEncoder::new(&mut cursor);
                 encoder
                     .write_image(
                         rgba.as_raw(),
@@ -174,13 +243,36 @@ impl Format {
                         image::ExtendedColorType::Rgba8,
                     )
                     .map_err(|e| {
-                        IiifError::InternalServerError(format!("Failed to encode WebP image: {e}"))
+                        IiifError::InternalServerError(format!("Failed to encode GIF image: {e}"))
                     })?;
             }
-            Format::Gif => {
+            Format::Tif => {
+                if let Some(tiled) = &config.tiff {
+                    bytes = tiled.encode(&image, icc_profile)?;
+                } else {
+                    // 16 位图像保持 16 位输出，保留完整动态范围
+                    let image = lossless_layout(image, icc_profile, false);
+                    let mut cursor = Cursor::new(&mut bytes);
+                    let mut encoder = TiffEncoder::new(&mut cursor);
+                    set_icc_profile(&mut encoder, icc_profile)?;
+                    image.write_with_encoder(encoder).map_err(|e| {
+                        IiifError::InternalServerError(format!("Failed to encode TIF image: {e}"))
+                    })?;
+                }
+            }
+            Format::Jp2 => {
+                return Err(IiifError::NotImplemented(
+                    "JPEG 2000 encoding not yet implemented".to_string(),
+                ));
+            }
+            Format::Pdf => {
+                bytes = config.pdf.render(&image, icc_profile)?;
+            }
+            #[cfg(feature = "avif")]
+            Format::Avif => {
+                // 速度 8（1-10）兼顾瓦片请求的编码耗时和压缩率
                 let rgba = image.to_rgba8();
-                let mut cursor = Cursor::new(&mut bytes);
-                let encoder = GifEncoder::new(&mut cursor);
+                let encoder = AvifEncoder::new_with_speed_quality(&mut bytes, 8, 80);
                 encoder
                     .write_image(
                         rgba.as_raw(),
@@ -189,13 +281,14 @@ impl Format {
                         image::ExtendedColorType::Rgba8,
                     )
                     .map_err(|e| {
-                        IiifError::InternalServerError(format!("Failed to encode GIF image: {e}"))
+                        IiifError::InternalServerError(format!("Failed to encode AVIF image: {e}"))
                     })?;
             }
-            Format::Tif => {
+            #[cfg(feature = "bmp")]
+            Format::Bmp => {
                 let rgba = image.to_rgba8();
                 let mut cursor = Cursor::new(&mut bytes);
-                let encoder = TiffEncoder::new(&mut cursor);
+                let encoder = BmpEncoder::new(&mut cursor);
                 encoder
                     .write_image(
                         rgba.as_raw(),
@@ -204,124 +297,75 @@ impl Format {
                         image::ExtendedColorType::Rgba8,
                     )
                     .map_err(|e| {
-                        IiifError::InternalServerError(format!("Failed to encode TIF image: {e}"))
+                        IiifError::InternalServerError(format!("Failed to encode BMP image: {e}"))
                     })?;
             }
-            Format::Jp2 => {
+            #[cfg(not(feature = "avif"))]
+            Format::Avif => {
                 return Err(IiifError::NotImplemented(
-                    "JPEG 2000 encoding not yet implemented".to_string(),
+                    "AVIF encoding requires the `avif` feature".to_string(),
                 ));
             }
-            Format::Pdf => {
-                // 将图像转换为 JPEG 格式（PDF 中 JPEG 更小）
-                let rgb = image.to_rgb8();
-                let mut jpeg_data = Vec::new();
-                {
-                    let mut jpeg_cursor = Cursor::new(&mut jpeg_data);
-                    let encoder = JpegEncoder::new(&mut jpeg_cursor);
-                    encoder
-                        .write_image(
-                            rgb.as_raw(),
-                            rgb.width(),
-                            rgb.height(),
-                            image::ExtendedColorType::Rgb8,
-                        )
-                        .map_err(|e| {
-                            IiifError::InternalServerError(format!(
-                                "Failed to encode JPEG image: {e}"
-                            ))
-                        })?;
-                }
-
-                // 创建 PDF 文档
-                let mut doc = Document::with_version("1.5");
-
-                // 创建图像字典
-                let width = rgb.width() as f64;
-                let height = rgb.height() as f64;
-
-                // 创建图像 XObject
-                let image_dict = dictionary! {
-                    "Type" => "XObject",
-                    "Subtype" => "Image",
-                    "Width" => rgb.width() as i64,
-                    "Height" => rgb.height() as i64,
-                    "ColorSpace" => "DeviceRGB",
-                    "BitsPerComponent" => 8,
-                    "Filter" => "DCTDecode", // JPEG 使用 DCTDecode
-                };
-
-                let image_stream = Stream::new(image_dict, jpeg_data);
-                let image_id = doc.add_object(image_stream);
-
-                // 创建页面内容流
-                // q: 保存图形状态, cm: 变换矩阵, Do: 绘制XObject, Q: 恢复图形状态
-                let content = format!("q\n{width} 0 0 {height} 0 0 cm\n/Im1 Do\nQ");
-                let content_stream = Stream::new(dictionary! {}, content.into_bytes());
-                let content_id = doc.add_object(content_stream);
-
-                // 先创建页面树（空），获取其 ID
-                let pages_id = doc.new_object_id();
-                let pages = dictionary! {
-                    "Type" => "Pages",
-                    "Kids" => vec![],
-                    "Count" => 0,
-                };
-                doc.objects.insert(pages_id, Object::Dictionary(pages));
-
-                // 创建页面对象，并设置父引用
-                let page = dictionary! {
-                    "Type" => "Page",
-                    "Parent" => Object::Reference(pages_id),
-                    "MediaBox" => vec![0.into(), 0.into(), width.into(), height.into()],
-                    "Resources" => dictionary! {
-                        "XObject" => dictionary! {
-                            "Im1" => image_id,
-                        },
-                    },
-                    "Contents" => content_id,
-                };
-                let page_id = doc.add_object(page);
-
-                // 更新页面树，添加页面引用并更新计数
-                if let Ok(pages_dict) = doc.get_dictionary_mut(pages_id) {
-                    if let Ok(kids) = pages_dict.get_mut(b"Kids") {
-                        if let Ok(kids_array) = kids.as_array_mut() {
-                            kids_array.push(Object::Reference(page_id));
-                        } else {
-                            // 如果 Kids 不存在，创建它
-                            pages_dict.set("Kids", vec![Object::Reference(page_id)]);
-                        }
-                    } else {
-                        pages_dict.set("Kids", vec![Object::Reference(page_id)]);
-                    }
-                    pages_dict.set("Count", 1);
-                }
-
-                // 创建目录
-                let catalog = dictionary! {
-                    "Type" => "Catalog",
-                    "Pages" => Object::Reference(pages_id),
-                };
-                let catalog_id = doc.add_object(catalog);
+            #[cfg(not(feature = "bmp"))]
+            Format::Bmp => {
+                return Err(IiifError::NotImplemented(
+                    "BMP encoding requires the `bmp` feature".to_string(),
+                ));
+            }
+        }
 
-                // 设置文档根和 trailer
-                doc.trailer.set("Root", Object::Reference(catalog_id));
-                doc.trailer.set("Size", (doc.objects.len() + 1) as i64);
+        Ok(bytes)
+    }
+}
 
-                // 将文档写入字节流
-                doc.save_to(&mut bytes).map_err(|e| {
-                    IiifError::InternalServerError(format!("Failed to save PDF document: {e}"))
-                })?;
+/// PNG 和 TIFF 输出的像素布局：高位深图像保持 16 位。嵌入灰度配置文件时输出灰度，透明通道仅在
+/// `gray_alpha` 时保留（TIFF 编码器不支持灰度加透明）；没有配置文件时 16 位灰度图像保持单通道，
+/// 其余图像转换为 RGB(A)
+fn lossless_layout(
+    image: DynamicImage,
+    icc_profile: Option<&[u8]>,
+    gray_alpha: bool,
+) -> DynamicImage {
+    if icc_profile.is_some_and(is_gray_profile) {
+        let alpha = gray_alpha && image.color().has_alpha();
+        return match (is_high_bit_depth(&image), alpha) {
+            (true, true) => DynamicImage::ImageLumaA16(image.to_luma_alpha16()),
+            (true, false) => DynamicImage::ImageLuma16(image.to_luma16()),
+            (false, true) => DynamicImage::ImageLumaA8(image.to_luma_alpha8()),
+            (false, false) => DynamicImage::ImageLuma8(image.to_luma8()),
+        };
+    }
+    match image {
+        DynamicImage::ImageLuma16(_) if icc_profile.is_none() => image,
+        DynamicImage::ImageRgb16(_) | DynamicImage::ImageRgba16(_) => image,
+        image if is_high_bit_depth(&image) => {
+            if image.color().has_alpha() {
+                DynamicImage::ImageRgba16(image.to_rgba16())
+            } else {
+                DynamicImage::ImageRgb16(image.to_rgb16())
             }
         }
+        image => DynamicImage::ImageRgba8(image.to_rgba8()),
+    }
+}
 
-        Ok(bytes)
+/// 在编码器中嵌入 ICC 配置文件
+fn set_icc_profile(
+    encoder: &mut impl ImageEncoder,
+    icc_profile: Option<&[u8]>,
+) -> Result<(), IiifError> {
+    if let Some(icc_profile) = icc_profile {
+        encoder.set_icc_profile(icc_profile.to_vec()).map_err(|e| {
+            IiifError::InternalServerError(format!("Failed to embed ICC profile: {e}"))
+        })?;
     }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use lopdf::Document;
+
     use crate::storage::{LocalStorage, Storage};
 
     use super::*;
@@ -406,4 +450,104 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_format_extensions() {
+        for (format, name, content_type) in [
+            (Format::Avif, "avif", "image/avif"),
+            (Format::Bmp, "bmp", "image/bmp"),
+        ] {
+            assert_eq!(Format::from_str(name).unwrap(), format);
+            assert_eq!(format.to_string(), name);
+            assert_eq!(format.get_content_type(), content_type);
+        }
+
+        // 未启用 feature 时变体仍然存在，但无法编码
+        #[cfg(not(feature = "avif"))]
+        assert!(matches!(
+            Format::Avif.encode(DynamicImage::new_rgb8(4, 4), None),
+            Err(IiifError::NotImplemented(_))
+        ));
+        #[cfg(not(feature = "bmp"))]
+        assert!(matches!(
+            Format::Bmp.encode(DynamicImage::new_rgb8(4, 4), None),
+            Err(IiifError::NotImplemented(_))
+        ));
+    }
+
+    #[cfg(feature = "avif")]
+    #[test]
+    fn test_format_avif() {
+        assert_eq!(Format::from_str("avif").unwrap(), Format::Avif);
+        assert_eq!(Format::Avif.to_string(), "avif");
+        assert_eq!(Format::Avif.get_content_type(), "image/avif");
+
+        let image = image::open("./fixtures/demo.jpg").unwrap();
+        let data = Format::Avif.encode(image, None).unwrap();
+        assert_eq!(&data[4..12], b"ftypavif");
+    }
+
+    #[cfg(feature = "bmp")]
+    #[test]
+    fn test_format_bmp() {
+        assert_eq!(Format::from_str("bmp").unwrap(), Format::Bmp);
+        assert_eq!(Format::Bmp.get_content_type(), "image/bmp");
+
+        let image = image::open("./fixtures/demo.jpg").unwrap();
+        let data = Format::Bmp.encode(image.clone(), None).unwrap();
+        let decoded = image::load_from_memory(&data).unwrap();
+        assert_eq!(decoded.to_rgb8(), image.to_rgb8());
+    }
+
+    #[test]
+    fn test_format_encode_icc_profile() {
+        let data = std::fs::read("./fixtures/adobe-rgb.jpg").unwrap();
+        let source = crate::image::Source::new(&data, true).unwrap();
+        let icc_profile = source.icc_profile().unwrap();
+        for format in [Format::Jpg, Format::Png, Format::Tif, Format::Webp] {
+            let image = source.decode().unwrap();
+            let result = format.encode(image, Some(icc_profile)).unwrap();
+            let output = crate::image::Source::new(&result, true).unwrap();
+            assert_eq!(output.icc_profile(), Some(icc_profile), "{format}");
+        }
+
+        let image = source.decode().unwrap();
+        let result = Format::Pdf.encode(image, Some(icc_profile)).unwrap();
+        let doc = Document::load_mem(&result).unwrap();
+        let icc_stream = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_stream().ok())
+            .find(|stream| stream.dict.get(b"N").is_ok())
+            .unwrap();
+        assert_eq!(icc_stream.content, icc_profile);
+    }
+
+    #[test]
+    fn test_format_encode_gray_profile() {
+        let icc_profile = moxcms::ColorProfile::new_gray_with_gamma(2.2)
+            .encode()
+            .unwrap();
+        // image 读取 TIFF 标签时以像素数据大小为上限，图像不能太小
+        let image = DynamicImage::ImageLuma8(image::GrayImage::from_fn(256, 256, |x, y| {
+            image::Luma([(x + y) as u8])
+        }));
+        // 嵌入灰度配置文件的输出保持灰度
+        for format in [Format::Jpg, Format::Png, Format::Tif] {
+            let result = format.encode(image.clone(), Some(&icc_profile)).unwrap();
+            let output = crate::image::Source::new(&result, true).unwrap();
+            assert_eq!(
+                output.icc_profile(),
+                Some(icc_profile.as_slice()),
+                "{format}"
+            );
+            let decoded = output.decode().unwrap();
+            assert!(!decoded.color().has_color(), "{format}");
+        }
+
+        // WebP 只能存储 RGB，灰度配置文件转换后不嵌入
+        let result = Format::Webp.encode(image, Some(&icc_profile)).unwrap();
+        let output = crate::image::Source::new(&result, true).unwrap();
+        assert!(output.icc_profile().is_none());
+    }
 }
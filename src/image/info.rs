@@ -8,7 +8,7 @@ use crate::{
 /// ImageInfo 定义了 IIIF 图像的基本信息
 ///
 /// Several technical properties
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ImageInfo {
     /// `@context` 属性应作为 JSON 表示的第一个键值对出现。它的值必须是 URI `http://iiif.io/api/image/3/context.json`
@@ -212,7 +212,7 @@ impl Default for ImageInfo {
 ///
 /// A string indicating the highest compliance level which is fully supported by the service.
 /// The value must be one of `level0`, `level1`, or `level2`.
-#[derive(Debug, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum Profile {
     #[default]
@@ -224,7 +224,7 @@ pub enum Profile {
 /// 尺寸项，表示图像的宽度和高度。
 ///
 /// A size item, representing the width and height of the image.
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SizeInfo {
     /// 对象类型，如果存在，值必须为字符串 `Size`。
     ///
@@ -246,7 +246,7 @@ pub struct SizeInfo {
 /// 尺寸类型，如果存在，值必须为字符串 `size`。
 ///
 /// The object type, if present, must be the string `size`.
-#[derive(Debug, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "PascalCase")]
 pub enum SizeType {
     #[default]
@@ -256,7 +256,7 @@ pub enum SizeType {
 /// 瓦片项，表示图像的瓦片信息。
 ///
 /// A tile item, representing the tile information of the image.
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TileInfo {
     /// 对象类型，如果存在，值必须为字符串 `Tile`。
@@ -292,14 +292,14 @@ pub struct TileInfo {
 /// 瓦片类型，如果存在，值必须为字符串 `Tile`。
 ///
 /// The object type, if present, must be the string `Tile`.
-#[derive(Debug, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "PascalCase")]
 pub enum TileType {
     #[default]
     Tile,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum Feature {
     /// 服务的基础 URI 将重定向到图像信息文档
@@ -0,0 +1,75 @@
+use image::{DynamicImage, GrayImage, ImageFormat, RgbImage};
+use jpeg_decoder::{Decoder, PixelFormat};
+
+/// 利用 DCT 缩放将 JPEG 直接解码为 1/`denominator` 尺寸（`denominator` 为 2、4 或 8）
+///
+/// Decodes a JPEG at 1/`denominator` of its size (2, 4 or 8) with the DCT scaling of
+/// `jpeg-decoder`, so that only the low-frequency coefficients of each block are transformed.
+/// The output is `ceil(width / denominator)` x `ceil(height / denominator)` pixels. Returns
+/// `None` for other formats, 16-bit and CMYK images, or if the decoder fails, in which case
+/// the caller decodes the image in full.
+pub(crate) fn decode_scaled(data: &[u8], denominator: u32) -> Option<DynamicImage> {
+    if !matches!(denominator, 2 | 4 | 8)
+        || image::guess_format(data).ok() != Some(ImageFormat::Jpeg)
+    {
+        return None;
+    }
+    let mut decoder = Decoder::new(data);
+    decoder.read_info().ok()?;
+    let info = decoder.info()?;
+    let width = u32::from(info.width).div_ceil(denominator);
+    let height = u32::from(info.height).div_ceil(denominator);
+    let scaled = decoder.scale(width as u16, height as u16).ok()?;
+    if (u32::from(scaled.0), u32::from(scaled.1)) != (width, height) {
+        return None;
+    }
+    let pixels = match decoder.decode() {
+        Ok(pixels) => pixels,
+        Err(e) => {
+            tracing::debug!(error = %e, "scaled JPEG decode failed, decoding in full");
+            return None;
+        }
+    };
+    match info.pixel_format {
+        PixelFormat::L8 => GrayImage::from_raw(width, height, pixels).map(DynamicImage::from),
+        PixelFormat::RGB24 => RgbImage::from_raw(width, height, pixels).map(DynamicImage::from),
+        PixelFormat::L16 | PixelFormat::CMYK32 => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_scaled() {
+        let data = std::fs::read("./fixtures/demo.jpg").unwrap();
+        let full = image::load_from_memory(&data).unwrap().to_rgb8();
+        for (denominator, dimensions) in [(2, (150, 100)), (4, (75, 50)), (8, (38, 25))] {
+            let image = decode_scaled(&data, denominator).unwrap();
+            assert_eq!((image.width(), image.height()), dimensions);
+
+            // 与完整解码后按块取平均的结果相近
+            let image = image.to_rgb8();
+            let error = image.enumerate_pixels().map(|(x, y, pixel)| {
+                let (x0, y0) = (x * denominator, y * denominator);
+                let (x1, y1) = ((x0 + denominator).min(300), (y0 + denominator).min(200));
+                let count = (x1 - x0) * (y1 - y0);
+                let sum = (0..3).map(|c| {
+                    let total: u32 = (y0..y1)
+                        .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+                        .map(|(x, y)| u32::from(full.get_pixel(x, y).0[c]))
+                        .sum();
+                    (total / count).abs_diff(u32::from(pixel.0[c]))
+                });
+                u64::from(sum.sum::<u32>())
+            });
+            let error = error.sum::<u64>() as f64 / (image.len() as f64);
+            assert!(error < 12.0, "1/{denominator}: {error}");
+        }
+
+        assert!(decode_scaled(&data, 3).is_none());
+        let gif = std::fs::read("./fixtures/pages.gif").unwrap();
+        assert!(decode_scaled(&gif, 2).is_none());
+    }
+}
@@ -2,29 +2,100 @@
 //!
 //! [官方文档(Official Documentation)](https://iiif.io/api/image/3.0/)
 //!
+mod backend;
+mod builder;
+mod canonical;
+mod color;
+mod flight;
 mod format;
 mod info;
+mod jpeg;
+mod page;
+mod plan;
+mod pyramid;
 mod quality;
 mod region;
+mod request;
+mod resize;
 mod result;
 mod rotation;
+mod service;
 mod size;
+mod source;
+mod tile;
+mod tiled;
+mod v2;
 
-use std::{fmt::Display, str::FromStr};
+use std::{fmt::Display, str::FromStr, sync::LazyLock, time::Instant};
 
+pub use backend::*;
+pub use builder::*;
+pub use color::*;
 pub use format::*;
 pub use info::*;
+pub use page::*;
+pub use plan::*;
+pub use pyramid::*;
 pub use quality::*;
 pub use region::*;
+pub use request::*;
+pub use resize::*;
 pub use result::*;
 pub use rotation::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+pub use service::*;
 pub use size::*;
+pub use source::*;
+pub use tile::*;
+pub use tiled::*;
 use url::Url;
+pub use v2::*;
 
 use crate::storage::Storage;
+use crate::telemetry::{self, Stage};
+use flight::SingleFlight;
+
+/// 正在处理中的请求，用于合并相同的并发请求
+static IN_FLIGHT: LazyLock<SingleFlight<Result<(ProcessResult, bool), crate::IiifError>>> =
+    LazyLock::new(SingleFlight::new);
+
+/// 以 IIIF 字符串形式进行序列化和反序列化
+macro_rules! impl_serde_as_str {
+    ($($ty:ty),*) => {$(
+        impl Serialize for $ty {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let value = String::deserialize(deserializer)?;
+                value.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    )*};
+}
+
+impl_serde_as_str!(Region, Size, Rotation);
 
 /// IiifImage 定义了 IIIF 图像的基本信息
-#[derive(Debug)]
+///
+/// 参数以 IIIF 字符串形式序列化，缺省的字段取默认值（`full/max/0/default.jpg`），
+/// 可用于在配置文件或任务队列中描述衍生图。
+///
+/// Parameters serialize to their IIIF string forms, and missing fields take the defaults
+/// of `full/max/0/default.jpg`, so derivative recipes can be stored as data.
+///
+/// ```
+/// use i3f::image::{IiifImage, Region};
+///
+/// let image: IiifImage = serde_json::from_str(r#"{"region":"square","size":"!200,200"}"#).unwrap();
+/// assert_eq!(image.region, Region::Square);
+/// assert_eq!(image.to_string(), "/square/!200,200/0/default.jpg");
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct IiifImage {
     pub identifier: String,
     pub region: Region,
@@ -34,11 +105,42 @@ pub struct IiifImage {
     pub format: Format,
 }
 
+impl Default for IiifImage {
+    fn default() -> Self {
+        Self {
+            identifier: String::new(),
+            region: Region::Full,
+            size: Size::Max,
+            rotation: Rotation::Degrees(0.0),
+            quality: Quality::Default,
+            format: Format::Jpg,
+        }
+    }
+}
+
 impl TryFrom<Url> for IiifImage {
     type Error = crate::IiifError;
 
     fn try_from(url: Url) -> Result<Self, Self::Error> {
-        // 获取 url path 的最后四项
+        Self::try_from_url(url, ApiVersion::V3)
+    }
+}
+
+fn url_decode(value: &str) -> Result<String, crate::IiifError> {
+    let decoded = urlencoding::decode(value)
+        .map_err(|_| crate::IiifError::BadRequest(format!("Invalid identifier: {value}")))?;
+    Ok(decoded.to_string())
+}
+
+fn url_encode(value: &str) -> String {
+    let encoded = urlencoding::encode(value);
+    encoded.to_string()
+}
+
+impl IiifImage {
+    /// 按指定的 API 版本解析 URL
+    fn try_from_url(url: Url, version: ApiVersion) -> Result<Self, crate::IiifError> {
+        // 获取 url path 的最后五项
         let url_segs = url
             .path_segments()
             .map(|segments| segments.collect::<Vec<_>>())
@@ -49,32 +151,40 @@ impl TryFrom<Url> for IiifImage {
             ));
         }
         let params = &url_segs[url_segs.len() - 5..];
+        let identifier = url_decode(params[0])?;
+        Self::from_params(identifier, &params[1..], version)
+    }
+
+    /// 根据已解码的标识符和 region/size/rotation/quality.format 四段参数构建请求
+    fn from_params(
+        identifier: String,
+        params: &[&str],
+        version: ApiVersion,
+    ) -> Result<Self, crate::IiifError> {
         // 解析质量和格式（最后一段包含点分隔符）
-        let (quality_str, format_str) = Self::parse_quality_format(params[4])?;
+        let (quality_str, format_str) = Self::parse_quality_format(params[3])?;
+
+        let (size, quality) = match version {
+            ApiVersion::V2 => (
+                Self::parse_with(params[1], "size", Size::from_v2_str)?,
+                Self::parse_with(quality_str, "quality", Quality::from_v2_str)?,
+            ),
+            ApiVersion::V3 => (
+                Self::parse_param(params[1], "size")?,
+                Self::parse_param(quality_str, "quality")?,
+            ),
+        };
 
         Ok(IiifImage {
-            identifier: Self::validate_identifier(url_decode(params[0])?.as_str())?,
-            region: Self::parse_param(params[1], "region")?,
-            size: Self::parse_param(params[2], "size")?,
-            rotation: Self::parse_param(params[3], "rotation")?,
-            quality: Self::parse_param(quality_str, "quality")?,
+            identifier: Self::validate_identifier(&identifier)?,
+            region: Self::parse_param(params[0], "region")?,
+            size,
+            rotation: Self::parse_param(params[2], "rotation")?,
+            quality,
             format: Self::parse_param(format_str, "format")?,
         })
     }
-}
-
-fn url_decode(value: &str) -> Result<String, crate::IiifError> {
-    let decoded = urlencoding::decode(value)
-        .map_err(|_| crate::IiifError::BadRequest(format!("Invalid identifier: {value}")))?;
-    Ok(decoded.to_string())
-}
 
-fn url_encode(value: &str) -> String {
-    let encoded = urlencoding::encode(value);
-    encoded.to_string()
-}
-
-impl IiifImage {
     /// 验证标识符
     fn validate_identifier(identifier: &str) -> Result<String, crate::IiifError> {
         if identifier.is_empty() {
@@ -107,9 +217,28 @@ impl IiifImage {
         })
     }
 
-    /// 对图片进行处理
+    /// 使用指定的解析函数解析参数
+    fn parse_with<T>(
+        value: &str,
+        param_name: &str,
+        parse: fn(&str) -> Result<T, crate::IiifError>,
+    ) -> Result<T, crate::IiifError> {
+        parse(value).map_err(|_| {
+            crate::IiifError::InvalidIiifURL(format!("Invalid {param_name} format: {value}"))
+        })
+    }
+
+    /// 对图片进行处理，使用 `ServiceConfig::default()`
+    ///
+    /// 默认配置会按 EXIF 方向信息校正原图，并将带有 ICC 配置文件的原图转换为 sRGB，
+    /// 与早期版本直接使用原始像素的行为不同。需要旧行为时使用 [`IiifImage::process_with`]，
+    /// 并设置 `auto_orient: false`。
     ///
-    /// Returns the processed image data as a vector of bytes.
+    /// Returns the processed image data as a vector of bytes, using `ServiceConfig::default()`.
+    /// The default configuration rotates sources to their EXIF display orientation and
+    /// converts sources with an embedded ICC profile to sRGB, whereas earlier versions used
+    /// the raw decoded pixels. Call [`IiifImage::process_with`] with `auto_orient: false` and
+    /// the desired `color` strategy to opt out.
     ///
     /// Example:
     /// ```
@@ -125,37 +254,171 @@ impl IiifImage {
     /// let image_data = image.process(&storage).unwrap();
     /// ```
     pub fn process(&self, storage: &dyn Storage) -> Result<ProcessResult, crate::IiifError> {
+        self.process_with(storage, &ServiceConfig::default())
+    }
+
+    /// 按服务能力配置对图片进行处理，服务不支持的请求返回 `IiifError::NotFound`
+    ///
+    /// Processes the image with the given service configuration. Requests using features,
+    /// qualities or formats the service does not support, or giving a width or height above
+    /// its size limits, are rejected by [`ServiceConfig::check`] before the source is fetched.
+    /// The size limits that depend on the source dimensions, such as those of `max`, `pct:n`
+    /// or `!w,h`, are applied once the source headers have been read, before decoding.
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::IiifError;
+    /// use i3f::image::{IiifImage, ServiceConfig};
+    /// use i3f::storage::LocalStorage;
+    /// use url::Url;
+    ///
+    /// let url = Url::parse("https://example.org/image-service/demo.jpg/full/max/90/default.jpg").unwrap();
+    /// let image = IiifImage::try_from(url).unwrap();
+    /// let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+    /// let result = image.process_with(&storage, &ServiceConfig::level1());
+    /// assert!(matches!(result, Err(IiifError::NotFound(_))));
+    /// ```
+    pub fn process_with(
+        &self,
+        storage: &dyn Storage,
+        config: &ServiceConfig,
+    ) -> Result<ProcessResult, crate::IiifError> {
+        let _span = tracing::info_span!("iiif_process", request = %self).entered();
+        let start = Instant::now();
+
+        // 服务不支持的请求直接拒绝
+        config.check(self)?;
+
         // 如果 iiif 文件存在，则直接返回
         if let Ok(iiif_file) = storage.get_iiif_file(self) {
+            record_response(true, &iiif_file, start);
             return Ok(iiif_file);
         }
 
-        // 获取原始文件
-        let origin_file = storage
-            .get_origin_file(&self.identifier)
+        let result = self.generate(storage, config);
+        match result {
+            Ok((result, cached)) => {
+                record_response(cached, &result, start);
+                Ok(result)
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to process image");
+                Err(e)
+            }
+        }
+    }
+
+    /// 生成 iiif 文件并保存
+    ///
+    /// 衍生文件以规范形式（canonical）的请求作为缓存键，等价的请求共享同一个文件。
+    /// 非规范形式的请求会另存一份，之后的相同请求无需读取原图即可命中缓存。
+    /// 返回值中的布尔值表示结果是否来自缓存。
+    fn generate(
+        &self,
+        storage: &dyn Storage,
+        config: &ServiceConfig,
+    ) -> Result<(ProcessResult, bool), crate::IiifError> {
+        // 获取原始文件，多页原图的页码写在标识符中
+        let (file, page) = split_page(&self.identifier)?;
+        let origin_file = Stage::Fetch
+            .run(|| storage.get_origin_file(file))
             .map_err(crate::IiifError::InternalServerError)?;
-        let image = image::load_from_memory(&origin_file)
-            .map_err(|e| crate::IiifError::InternalServerError(e.to_string()))?;
-        // 处理 region 数据
-        let image = self.region.process(image)?;
-        // 处理 size 数据
-        let image = self.size.process(image)?;
-        // 处理 rotation 数据
-        let image = self.rotation.process(image)?;
-        let image = self.quality.process(image)?;
-        let result = self.format.process(image)?;
+        metrics::counter!(telemetry::ORIGIN_BYTES).increment(origin_file.len() as u64);
+
+        // 转换为规范形式
+        let source = Source::page(&origin_file, page, config.auto_orient)?;
+        let (width, height) = source.dimensions();
+        let plan = self.plan(width, height, &config.limits)?;
+        let canonical = self.canonical_for(&plan);
+
+        // 相同存储和配置下等价的请求只处理一次，其余请求等待并共享结果
+        let key = format!(
+            "{}/{}/{}",
+            storage.cache_key(),
+            config.fingerprint(),
+            canonical
+        );
+        let (result, cached) =
+            IN_FLIGHT.run(&key, || canonical.render(storage, config, &source, &plan))?;
+
+        // 非规范形式的请求同时保存一份
+        if canonical != *self
+            && let Err(e) = Stage::Save.run(|| storage.save_iiif_file(self, &result.data))
+        {
+            tracing::warn!(error = %e, "failed to save requested form");
+        }
+        Ok((result, cached))
+    }
+
+    /// 按处理计划生成规范形式的 iiif 文件并保存，已存在时直接返回
+    fn render(
+        &self,
+        storage: &dyn Storage,
+        config: &ServiceConfig,
+        source: &Source,
+        plan: &ImagePlan,
+    ) -> Result<(ProcessResult, bool), crate::IiifError> {
+        // 等待期间可能已由其他请求生成
+        if let Ok(iiif_file) = storage.get_iiif_file(self) {
+            return Ok((iiif_file, true));
+        }
+
+        // 按处理计划解码，JPEG 可在解码时直接缩小
+        let (image, plan) = Stage::Decode.run(|| source.decode_for(plan))?;
+        // 按处理计划裁剪
+        let backend = &config.backend;
+        let image = Stage::Region.run(|| {
+            let crop = plan.crop;
+            if plan.is_full_region() {
+                Ok(image)
+            } else {
+                backend.crop(image, crop.x, crop.y, crop.width, crop.height)
+            }
+        })?;
+        // 按处理计划缩放
+        let image = Stage::Size.run(|| {
+            if plan.needs_resize() {
+                backend.resize(image, plan.scaled_width, plan.scaled_height)
+            } else {
+                Ok(image)
+            }
+        })?;
+        // 色彩管理
+        let (image, icc_profile) =
+            Stage::Color.run(|| config.color.process(image, source.icc_profile()));
+        let image = Stage::Rotation.run(|| backend.rotate(image, &self.rotation))?;
+        let image = Stage::Quality.run(|| backend.convert(image, &self.quality))?;
+        let result = Stage::Encode
+            .run(|| backend.encode(image, &self.format, icc_profile.as_deref(), config))?;
         let content_type = self.format.get_content_type();
 
         // 保存 iiif 文件
-        storage
-            .save_iiif_file(self, &result)
+        Stage::Save
+            .run(|| storage.save_iiif_file(self, &result))
             .map_err(crate::IiifError::InternalServerError)?;
 
         // 返回结果
-        Ok(ProcessResult::new(content_type.to_string(), result))
+        Ok((ProcessResult::new(content_type.to_string(), result), false))
     }
 }
 
+/// 记录缓存命中情况、响应大小和处理耗时
+fn record_response(cached: bool, result: &ProcessResult, start: Instant) {
+    let source = if cached {
+        tracing::debug!("cache hit");
+        metrics::counter!(telemetry::CACHE_HITS).increment(1);
+        "cache"
+    } else {
+        tracing::debug!("cache miss");
+        metrics::counter!(telemetry::CACHE_MISSES).increment(1);
+        "generated"
+    };
+    metrics::counter!(telemetry::RESPONSE_BYTES, "source" => source)
+        .increment(result.data.len() as u64);
+    metrics::histogram!(telemetry::REQUEST_DURATION, "source" => source)
+        .record(start.elapsed().as_secs_f64());
+}
+
 impl Display for IiifImage {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -346,10 +609,284 @@ mod tests {
             // remove the out directory
             if case.0.contains("square") {
                 std::fs::remove_dir_all("./fixtures/out/demo.jpg/square/").unwrap();
+                std::fs::remove_dir_all("./fixtures/out/demo.jpg/50,0,200,200/").unwrap();
             }
         }
     }
 
+    #[test]
+    fn test_process_auto_orient() {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let url = "https://example.org/image-service/rotated.jpg/full/max/0/default.png";
+        let image = IiifImage::try_from(Url::parse(url).unwrap()).unwrap();
+        let cases = [(true, 200, 300), (false, 300, 200)];
+        for (auto_orient, width, height) in cases {
+            let config = ServiceConfig {
+                auto_orient,
+                ..Default::default()
+            };
+            let result = image.process_with(&storage, &config).unwrap();
+            let output = image::load_from_memory(&result.data).unwrap();
+            assert_eq!((output.width(), output.height()), (width, height));
+            std::fs::remove_dir_all("./fixtures/out/rotated.jpg/").unwrap();
+        }
+    }
+
+    #[test]
+    fn test_process_color_management() {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let url = "https://example.org/image-service/adobe-rgb.jpg/full/max/0/default.png";
+        let image = IiifImage::try_from(Url::parse(url).unwrap()).unwrap();
+        let origin = std::fs::read("./fixtures/adobe-rgb.jpg").unwrap();
+        let origin = Source::new(&origin, true).unwrap();
+        let raw = origin.decode().unwrap().to_rgb8();
+
+        let cases = [ColorManagement::Convert, ColorManagement::Preserve];
+        for color in cases {
+            let config = ServiceConfig {
+                color,
+                ..Default::default()
+            };
+            let result = image.process_with(&storage, &config).unwrap();
+            let output = Source::new(&result.data, true).unwrap();
+            let pixels = output.decode().unwrap().to_rgb8();
+            if color == ColorManagement::Preserve {
+                assert_eq!(output.icc_profile(), origin.icc_profile());
+                assert_eq!(pixels, raw);
+            } else {
+                assert!(output.icc_profile().is_none());
+                assert_ne!(pixels, raw);
+            }
+            std::fs::remove_dir_all("./fixtures/out/adobe-rgb.jpg/").unwrap();
+        }
+    }
+
+    /// 记录裁剪和缩放输入尺寸的后端
+    #[derive(Debug, Default)]
+    struct Sizes(std::sync::Mutex<Vec<(u32, u32)>>);
+
+    impl Backend for Sizes {
+        fn name(&self) -> &str {
+            "sizes"
+        }
+
+        fn resize(
+            &self,
+            image: image::DynamicImage,
+            width: u32,
+            height: u32,
+        ) -> Result<image::DynamicImage, crate::IiifError> {
+            self.0.lock().unwrap().push((image.width(), image.height()));
+            ImageBackend.resize(image, width, height)
+        }
+    }
+
+    #[test]
+    fn test_process_decode_scale() {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        // 缩放前的区域尺寸反映解码倍数
+        let cases = [
+            ("demo.jpg/full/30,/0/default.png", 8, (38, 25), (30, 20)),
+            ("demo.jpg/full/!45,45/0/default.png", 4, (75, 50), (45, 30)),
+            (
+                "demo.jpg/61,33,150,100/37,/0/default.png",
+                4,
+                (38, 26),
+                (37, 25),
+            ),
+            (
+                "demo.jpg/full/150,/0/default.png",
+                2,
+                (150, 100),
+                (150, 100),
+            ),
+        ];
+        for (request, denominator, decoded, dimensions) in cases {
+            let url = format!("https://example.org/image-service/{request}");
+            let image = IiifImage::try_from(Url::parse(&url).unwrap()).unwrap();
+            let plan = image.plan(300, 200, &Limits::default()).unwrap();
+            assert_eq!(plan.decode_scale(), denominator, "{request}");
+            assert_eq!((plan.width, plan.height), dimensions, "{request}");
+
+            let backend = std::sync::Arc::new(Sizes::default());
+            let config = ServiceConfig {
+                backend: backend.clone(),
+                ..Default::default()
+            };
+            let result = image.process_with(&storage, &config).unwrap();
+            let output = image::load_from_memory(&result.data).unwrap();
+            assert_eq!((output.width(), output.height()), dimensions, "{request}");
+            let resized = backend.0.lock().unwrap().clone();
+            if decoded == dimensions {
+                assert!(resized.is_empty(), "{request}");
+            } else {
+                assert_eq!(resized, [decoded], "{request}");
+            }
+        }
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/full/30,20/").unwrap();
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/full/30,/").unwrap();
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/full/45,30/").unwrap();
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/full/!45,45/").unwrap();
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/61,33,150,100/").unwrap();
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/full/150,100/").unwrap();
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/full/150,/").unwrap();
+    }
+
+    #[test]
+    fn test_process_16bit() {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let origin = image::open("./fixtures/deep.tif").unwrap();
+        let cases = [
+            ("full/max/0/default.tif", origin.to_rgb16().into_raw()),
+            ("full/max/0/default.png", origin.to_rgb16().into_raw()),
+            ("0,0,32,24/max/180/gray.tif", {
+                let cropped = origin.crop_imm(0, 0, 32, 24).rotate180().grayscale();
+                cropped.to_luma16().into_raw()
+            }),
+        ];
+        for (params, expected) in cases {
+            let url = format!("https://example.org/image-service/deep.tif/{params}");
+            let image = IiifImage::try_from(Url::parse(&url).unwrap()).unwrap();
+            let result = image.process(&storage).unwrap();
+            let output = image::load_from_memory(&result.data).unwrap();
+            let pixels = match output {
+                image::DynamicImage::ImageRgb16(image) => image.into_raw(),
+                image::DynamicImage::ImageLuma16(image) => image.into_raw(),
+                output => panic!("{params}: unexpected color type {:?}", output.color()),
+            };
+            assert_eq!(pixels, expected, "{params}");
+        }
+
+        // JPEG 输出降为 8 位
+        let url = "https://example.org/image-service/deep.tif/full/max/0/default.jpg";
+        let image = IiifImage::try_from(Url::parse(url).unwrap()).unwrap();
+        let result = image.process(&storage).unwrap();
+        let output = image::load_from_memory(&result.data).unwrap();
+        assert_eq!(output.color(), image::ColorType::Rgb8);
+        std::fs::remove_dir_all("./fixtures/out/deep.tif/").unwrap();
+    }
+
+    #[test]
+    fn test_process_pages() {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let cases = [
+            ("pages.tif;page=2", 60, 40),
+            ("pages.pdf;page=2", 50, 40),
+            ("pages.pdf", 90, 60),
+            ("demo.jpg;page=1", 300, 200),
+        ];
+        for (identifier, width, height) in cases {
+            let image = IiifImage {
+                identifier: identifier.to_string(),
+                format: Format::Png,
+                ..Default::default()
+            };
+            let result = image.process(&storage).unwrap();
+            let output = image::load_from_memory(&result.data).unwrap();
+            assert_eq!((output.width(), output.height()), (width, height));
+        }
+        // 缓存目录使用编码后的标识符
+        for (identifier, _, _) in cases {
+            let dir = format!("./fixtures/out/{}", url_encode(identifier));
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+
+        let image = IiifImage {
+            identifier: "demo.jpg;page=2".to_string(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            image.process(&storage),
+            Err(crate::IiifError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_process_concurrent() {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let url = "https://example.org/image-service/demo.jpg/pct:10,10,50,50/max/0/default.png";
+        let results: Vec<ProcessResult> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    s.spawn(|| {
+                        let image = IiifImage::try_from(Url::parse(url).unwrap()).unwrap();
+                        image.process(&storage).unwrap()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        for result in &results {
+            assert_eq!(result.content_type, "image/png");
+            assert_eq!(result.data, results[0].data);
+        }
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/30,20,150,100/").unwrap();
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/pct:10,10,50,50/").unwrap();
+    }
+
+    /// 记录原图读取次数的存储
+    struct CountingStorage {
+        inner: LocalStorage,
+        origin_reads: std::sync::atomic::AtomicUsize,
+    }
+
+    impl Storage for CountingStorage {
+        fn get_origin_file(&self, identifier: &str) -> Result<Vec<u8>, String> {
+            self.origin_reads
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            self.inner.get_origin_file(identifier)
+        }
+
+        fn get_iiif_file(&self, params: &IiifImage) -> Result<ProcessResult, String> {
+            self.inner.get_iiif_file(params)
+        }
+
+        fn save_iiif_file(&self, params: &IiifImage, data: &[u8]) -> Result<(), String> {
+            self.inner.save_iiif_file(params, data)
+        }
+
+        fn cache_key(&self) -> String {
+            self.inner.cache_key()
+        }
+    }
+
+    #[test]
+    fn test_process_non_canonical_cache() {
+        let storage = CountingStorage {
+            inner: LocalStorage::new("./fixtures", "./fixtures/out"),
+            origin_reads: Default::default(),
+        };
+        let reads = || {
+            storage
+                .origin_reads
+                .load(std::sync::atomic::Ordering::SeqCst)
+        };
+        let cases = [
+            "pct:0,0,100,100/!40,40/0/default.png",
+            "full/!40,40/0/default.png",
+            "full/40,/0/default.png",
+        ];
+        let mut expected = None;
+        for (i, params) in cases.iter().enumerate() {
+            let url = format!("https://example.org/image-service/demo.jpg/{params}");
+            let image = IiifImage::try_from(Url::parse(&url).unwrap()).unwrap();
+            let first = image.process(&storage).unwrap();
+            // 每种写法只读取一次原图，第一种写法生成规范形式的文件
+            assert_eq!(reads(), i + 1, "{params}");
+            let second = image.process(&storage).unwrap();
+            assert_eq!(reads(), i + 1, "{params}");
+            assert_eq!(first.data, second.data);
+            let expected = expected.get_or_insert(first.data);
+            assert_eq!(&second.data, expected);
+        }
+        let canonical = "./fixtures/out/demo.jpg/full/40,27/";
+        assert!(std::fs::metadata(canonical).is_ok());
+        std::fs::remove_dir_all(canonical).unwrap();
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/pct:0,0,100,100/").unwrap();
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/full/!40,40/").unwrap();
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/full/40,/").unwrap();
+    }
+
     #[test]
     fn test_iiif_image() {
         let url = Url::parse("https://example.org/image-service/demo.jpg/full/max/0/default.jpg")
@@ -357,4 +894,31 @@ mod tests {
         let image = IiifImage::try_from(url).unwrap();
         assert_eq!(image.to_string(), "demo.jpg/full/max/0/default.jpg");
     }
+
+    #[test]
+    fn test_iiif_image_serde() {
+        let url = Url::parse(
+            "https://example.org/image-service/demo.jpg/pct:41.6,7.5,40,70/^!200,200/!22.5/gray.png",
+        )
+        .unwrap();
+        let image = IiifImage::try_from(url).unwrap();
+        let json = serde_json::to_value(&image).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "identifier": "demo.jpg",
+                "region": "pct:41.6,7.5,40,70",
+                "size": "^!200,200",
+                "rotation": "!22.5",
+                "quality": "gray",
+                "format": "png",
+            })
+        );
+        assert_eq!(serde_json::from_value::<IiifImage>(json).unwrap(), image);
+
+        let presets: Vec<Size> = serde_json::from_str(r#"["max", "150,", "pct:50"]"#).unwrap();
+        assert_eq!(presets[1], Size::W { w: 150 });
+        assert!(serde_json::from_str::<Region>(r#""pct:a,b""#).is_err());
+        assert!(serde_json::from_str::<Rotation>("90").is_err());
+    }
 }
@@ -0,0 +1,381 @@
+use std::io::Cursor;
+
+use image::codecs::gif::GifDecoder;
+use image::metadata::Orientation;
+use image::{AnimationDecoder, DynamicImage, ImageBuffer, ImageFormat};
+use lopdf::{Document, Object};
+use tiff::decoder::{Decoder as TiffDecoder, DecodingResult};
+use tiff::tags::Tag;
+
+use crate::IiifError;
+
+/// 标识符中的页码参数
+const PAGE_PARAM: &str = ";page=";
+
+/// 拆分标识符中的页码，页码从 1 开始，未指定时为第一页
+///
+/// Splits an identifier such as `volume1.tif;page=12` into the identifier of the source file
+/// and the 1-based page number. Identifiers without a page parameter address the first page.
+///
+/// Example:
+/// ```
+/// use i3f::image::split_page;
+///
+/// assert_eq!(split_page("volume1.tif;page=12").unwrap(), ("volume1.tif", 12));
+/// assert_eq!(split_page("demo.jpg").unwrap(), ("demo.jpg", 1));
+/// assert!(split_page("volume1.tif;page=0").is_err());
+/// ```
+pub fn split_page(identifier: &str) -> Result<(&str, u32), IiifError> {
+    let Some((file, page)) = identifier.rsplit_once(PAGE_PARAM) else {
+        return Ok((identifier, 1));
+    };
+    match page.parse::<u32>() {
+        Ok(page) if page > 0 && !file.is_empty() => Ok((file, page)),
+        _ => Err(IiifError::BadRequest(format!("Invalid page: {identifier}"))),
+    }
+}
+
+/// 原图的页数：TIFF 的 IFD 数、GIF 的帧数或 PDF 的页数，其他格式为 1
+///
+/// The number of pages of a source: the IFDs of a TIFF, the frames of a GIF or the pages of
+/// a PDF. Other formats have a single page.
+pub fn page_count(data: &[u8]) -> Result<u32, IiifError> {
+    if is_pdf(data) {
+        return Ok(load_pdf(data)?.get_pages().len() as u32);
+    }
+    match image::guess_format(data) {
+        Ok(ImageFormat::Tiff) => {
+            let mut decoder = tiff_decoder(data)?;
+            let mut count = 1;
+            while decoder.more_images() {
+                decoder.next_image().map_err(internal_error)?;
+                count += 1;
+            }
+            Ok(count)
+        }
+        Ok(ImageFormat::Gif) => {
+            let mut count = 0;
+            for frame in gif_frames(data)? {
+                frame.map_err(internal_error)?;
+                count += 1;
+            }
+            Ok(count)
+        }
+        _ => Ok(1),
+    }
+}
+
+/// 是否为 PDF 文件
+pub(crate) fn is_pdf(data: &[u8]) -> bool {
+    data.starts_with(b"%PDF")
+}
+
+/// TIFF 中某个 IFD 的头信息
+pub(crate) struct TiffPage {
+    pub width: u32,
+    pub height: u32,
+    pub orientation: Orientation,
+    pub icc_profile: Option<Vec<u8>>,
+}
+
+/// 读取 TIFF 第 `index` 个 IFD 的头信息（从 0 开始）
+pub(crate) fn tiff_page(data: &[u8], index: usize) -> Result<TiffPage, IiifError> {
+    let mut decoder = seek_tiff(data, index)?;
+    let (width, height) = decoder.dimensions().map_err(internal_error)?;
+    let orientation = decoder
+        .find_tag(Tag::Orientation)
+        .ok()
+        .flatten()
+        .and_then(|value| value.into_u16().ok())
+        .and_then(|value| Orientation::from_exif(value as u8))
+        .unwrap_or(Orientation::NoTransforms);
+    let icc_profile = decoder
+        .find_tag(Tag::IccProfile)
+        .ok()
+        .flatten()
+        .and_then(|value| value.into_u8_vec().ok());
+    Ok(TiffPage {
+        width,
+        height,
+        orientation,
+        icc_profile,
+    })
+}
+
+/// 解码 TIFF 第 `index` 个 IFD
+pub(crate) fn decode_tiff_page(data: &[u8], index: usize) -> Result<DynamicImage, IiifError> {
+    use tiff::ColorType;
+
+    let mut decoder = seek_tiff(data, index)?;
+    let (width, height) = decoder.dimensions().map_err(internal_error)?;
+    let color_type = decoder.colortype().map_err(internal_error)?;
+    let image = match (color_type, decoder.read_image().map_err(internal_error)?) {
+        (ColorType::Gray(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLuma8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLuma16)
+        }
+        (ColorType::GrayA(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLumaA8)
+        }
+        (ColorType::GrayA(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLumaA16)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgba8)
+        }
+        (ColorType::RGBA(16), DecodingResult::U16(buf)) => {
+            ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgba16)
+        }
+        (ColorType::YCbCr(8), DecodingResult::U8(buf)) => {
+            ImageBuffer::from_raw(width, height, ycbcr_to_rgb(buf)).map(DynamicImage::ImageRgb8)
+        }
+        (color_type, _) => {
+            return Err(IiifError::NotImplemented(format!(
+                "Unsupported TIFF color type: {color_type:?}"
+            )));
+        }
+    };
+    image.ok_or_else(|| internal_error("TIFF image data does not match its dimensions"))
+}
+
+/// JPEG 压缩的 TIFF 解码后仍为 YCbCr，按 JFIF 全范围公式转换为 RGB
+fn ycbcr_to_rgb(mut buf: Vec<u8>) -> Vec<u8> {
+    for pixel in buf.chunks_exact_mut(3) {
+        let y = pixel[0] as f32;
+        let cb = pixel[1] as f32 - 128.0;
+        let cr = pixel[2] as f32 - 128.0;
+        pixel[0] = (y + 1.402 * cr).round().clamp(0.0, 255.0) as u8;
+        pixel[1] = (y - 0.344136 * cb - 0.714136 * cr)
+            .round()
+            .clamp(0.0, 255.0) as u8;
+        pixel[2] = (y + 1.772 * cb).round().clamp(0.0, 255.0) as u8;
+    }
+    buf
+}
+
+/// 解码 GIF 第 `index` 帧（从 0 开始），返回合成到整个画布后的图像
+pub(crate) fn decode_gif_frame(data: &[u8], index: usize) -> Result<DynamicImage, IiifError> {
+    let frame = gif_frames(data)?
+        .nth(index)
+        .ok_or_else(|| page_not_found(index as u32 + 1))?
+        .map_err(internal_error)?;
+    Ok(DynamicImage::ImageRgba8(frame.into_buffer()))
+}
+
+/// PDF 页面中提取的图像
+pub(crate) enum PdfImage {
+    /// `DCTDecode` 编码的 JPEG 数据，以及 `ICCBased` 色彩空间中的配置文件
+    Jpeg(Vec<u8>, Option<Vec<u8>>),
+    /// 已解码的像素数据，以及 `ICCBased` 色彩空间中的配置文件
+    Decoded(DynamicImage, Option<Vec<u8>>),
+}
+
+/// 提取 PDF 第 `page` 页中面积最大的图像，扫描件每页通常只有一幅图像
+pub(crate) fn pdf_image(data: &[u8], page: u32) -> Result<PdfImage, IiifError> {
+    let doc = load_pdf(data)?;
+    let page_id = *doc
+        .get_pages()
+        .get(&page)
+        .ok_or_else(|| page_not_found(page))?;
+    let image = doc
+        .get_page_images(page_id)
+        .unwrap_or_default()
+        .into_iter()
+        .max_by_key(|image| image.width * image.height)
+        .ok_or_else(|| IiifError::NotFound(format!("No image on page {page}")))?;
+
+    let filters = image.filters.clone().unwrap_or_default();
+    let stream = doc
+        .get_object(image.id)
+        .and_then(Object::as_stream)
+        .map_err(internal_error)?;
+
+    // 色彩空间决定通道数，ICCBased 的通道数由配置文件流的 N 给出
+    let (channels, icc_profile) = match stream.dict.get(b"ColorSpace") {
+        Ok(Object::Name(name)) => (device_channels(name), None),
+        Ok(Object::Array(array))
+            if array.len() == 2 && array[0].as_name().ok() == Some(b"ICCBased".as_slice()) =>
+        {
+            let icc = array[1]
+                .as_reference()
+                .and_then(|id| doc.get_object(id))
+                .and_then(Object::as_stream)
+                .map_err(internal_error)?;
+            let channels = icc.dict.get(b"N").and_then(Object::as_i64).unwrap_or(0);
+            let profile = icc.decompressed_content().unwrap_or(icc.content.clone());
+            (channels as usize, Some(profile))
+        }
+        _ => (0, None),
+    };
+    let content = match filters.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["DCTDecode"] => return Ok(PdfImage::Jpeg(image.content.to_vec(), icc_profile)),
+        [] => image.content.to_vec(),
+        ["FlateDecode"] => stream.decompressed_content().map_err(internal_error)?,
+        _ => {
+            return Err(IiifError::NotImplemented(format!(
+                "Unsupported PDF image filter: {}",
+                filters.join(", ")
+            )));
+        }
+    };
+
+    let (width, height) = (image.width as u32, image.height as u32);
+    let bits = image.bits_per_component.unwrap_or(8);
+    let decoded = match (channels, bits) {
+        (1, 1) => ImageBuffer::from_raw(width, height, unpack_bits(&content, width, height))
+            .map(DynamicImage::ImageLuma8),
+        (1, 8) => ImageBuffer::from_raw(width, height, content).map(DynamicImage::ImageLuma8),
+        (3, 8) => ImageBuffer::from_raw(width, height, content).map(DynamicImage::ImageRgb8),
+        (1, 16) => ImageBuffer::from_raw(width, height, big_endian_u16(&content))
+            .map(DynamicImage::ImageLuma16),
+        (3, 16) => ImageBuffer::from_raw(width, height, big_endian_u16(&content))
+            .map(DynamicImage::ImageRgb16),
+        _ => {
+            return Err(IiifError::NotImplemented(format!(
+                "Unsupported PDF image color space with {channels} channels and {bits} bits"
+            )));
+        }
+    };
+    let decoded =
+        decoded.ok_or_else(|| internal_error("PDF image data does not match its dimensions"))?;
+    Ok(PdfImage::Decoded(decoded, icc_profile))
+}
+
+/// 设备色彩空间的通道数，不支持的色彩空间为 0
+fn device_channels(name: &[u8]) -> usize {
+    match name {
+        b"DeviceGray" | b"CalGray" => 1,
+        b"DeviceRGB" | b"CalRGB" => 3,
+        _ => 0,
+    }
+}
+
+/// 将每行按字节对齐的 1 位灰度数据展开为 8 位
+fn unpack_bits(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let row_bytes = (width as usize).div_ceil(8);
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for row in data.chunks(row_bytes).take(height as usize) {
+        for x in 0..width as usize {
+            let bit = row.get(x / 8).map_or(0, |byte| (byte >> (7 - x % 8)) & 1);
+            pixels.push(bit * 255);
+        }
+    }
+    pixels
+}
+
+/// PDF 中的 16 位数据为大端序
+fn big_endian_u16(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
+fn load_pdf(data: &[u8]) -> Result<Document, IiifError> {
+    Document::load_mem(data).map_err(internal_error)
+}
+
+fn tiff_decoder(data: &[u8]) -> Result<TiffDecoder<Cursor<&[u8]>>, IiifError> {
+    TiffDecoder::new(Cursor::new(data)).map_err(internal_error)
+}
+
+fn seek_tiff(data: &[u8], index: usize) -> Result<TiffDecoder<Cursor<&[u8]>>, IiifError> {
+    let mut decoder = tiff_decoder(data)?;
+    decoder
+        .seek_to_image(index)
+        .map_err(|_| page_not_found(index as u32 + 1))?;
+    Ok(decoder)
+}
+
+fn gif_frames(data: &[u8]) -> Result<image::Frames<'_>, IiifError> {
+    let decoder = GifDecoder::new(Cursor::new(data)).map_err(internal_error)?;
+    Ok(decoder.into_frames())
+}
+
+fn page_not_found(page: u32) -> IiifError {
+    IiifError::NotFound(format!("Page {page} not found"))
+}
+
+fn internal_error(e: impl std::fmt::Display) -> IiifError {
+    IiifError::InternalServerError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::Source;
+
+    use super::*;
+
+    #[test]
+    fn test_split_page() {
+        assert_eq!(split_page("a/b.tif;page=3").unwrap(), ("a/b.tif", 3));
+        assert_eq!(
+            split_page("b.tif;page=1;page=2").unwrap(),
+            ("b.tif;page=1", 2)
+        );
+        assert!(split_page("b.tif;page=").is_err());
+        assert!(split_page(";page=2").is_err());
+    }
+
+    #[test]
+    fn test_tiff_pages() {
+        let data = std::fs::read("./fixtures/pages.tif").unwrap();
+        assert_eq!(page_count(&data).unwrap(), 3);
+        let cases = [(1, 80, 50), (2, 60, 40), (3, 30, 20)];
+        for (page, width, height) in cases {
+            let source = Source::page(&data, page, true).unwrap();
+            assert_eq!(source.dimensions(), (width, height));
+            let image = source.decode().unwrap();
+            assert_eq!((image.width(), image.height()), (width, height));
+        }
+        let image = Source::page(&data, 2, true).unwrap().decode().unwrap();
+        assert_eq!(image.to_rgb16().get_pixel(3, 2).0, [3000, 3000, 40000]);
+        assert!(matches!(
+            Source::page(&data, 4, true),
+            Err(IiifError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_gif_frames() {
+        let data = std::fs::read("./fixtures/pages.gif").unwrap();
+        assert_eq!(page_count(&data).unwrap(), 2);
+        let image = Source::page(&data, 2, true).unwrap().decode().unwrap();
+        assert_eq!(image.to_rgb8().get_pixel(0, 0).0, [0, 0, 255]);
+        let source = Source::page(&data, 3, true).unwrap();
+        assert!(matches!(source.decode(), Err(IiifError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_pdf_images() {
+        let data = std::fs::read("./fixtures/pages.pdf").unwrap();
+        assert_eq!(page_count(&data).unwrap(), 3);
+
+        // 第一页为 JPEG，第二页为 Flate 压缩的 RGB，第三页为 1 位灰度
+        let first = Source::page(&data, 1, true).unwrap();
+        assert_eq!(first.dimensions(), (90, 60));
+        assert_eq!(first.decode().unwrap().width(), 90);
+
+        let second = Source::page(&data, 2, true).unwrap().decode().unwrap();
+        assert_eq!(second.to_rgb8().get_pixel(2, 3).0, [10, 18, 77]);
+
+        let third = Source::page(&data, 3, true).unwrap().decode().unwrap();
+        let third = third.to_luma8();
+        assert_eq!(third.dimensions(), (20, 10));
+        assert_eq!(third.get_pixel(7, 0).0, [0]);
+        assert_eq!(third.get_pixel(8, 0).0, [255]);
+        assert_eq!(third.get_pixel(19, 9).0, [255]);
+
+        assert!(matches!(
+            Source::page(&data, 4, true),
+            Err(IiifError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,471 @@
+use crate::IiifError;
+use crate::image::{IiifImage, ImageInfo, Rotation, Size};
+
+/// 服务端设定的尺寸限制，对应 `info.json` 中的 `maxWidth`、`maxHeight` 和 `maxArea`
+///
+/// Server-imposed size limits, matching `maxWidth`, `maxHeight` and `maxArea` of the
+/// image information document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// 最大宽度
+    ///
+    /// The maximum width in pixels.
+    pub max_width: Option<u32>,
+
+    /// 最大高度，未指定但指定了 `max_width` 时等于 `max_width`
+    ///
+    /// The maximum height in pixels, defaults to `max_width` when only that is given.
+    pub max_height: Option<u32>,
+
+    /// 最大面积
+    ///
+    /// The maximum area in pixels.
+    pub max_area: Option<u64>,
+}
+
+impl Limits {
+    /// 从图像信息中读取尺寸限制
+    ///
+    /// Reads the limits advertised by an image information document.
+    pub fn from_info(info: &ImageInfo) -> Self {
+        Self {
+            max_width: info.max_width,
+            max_height: info.max_height,
+            max_area: info.max_area.map(u64::from),
+        }
+    }
+
+    /// 是否未设置任何限制
+    pub fn is_unlimited(&self) -> bool {
+        self.max_width.is_none() && self.max_height.is_none() && self.max_area.is_none()
+    }
+
+    pub(crate) fn max_height(&self) -> Option<u32> {
+        self.max_height.or(self.max_width)
+    }
+
+    /// 判断尺寸是否在限制范围内
+    ///
+    /// Whether an output of `width` x `height` pixels is within the limits.
+    pub fn allows(&self, width: u32, height: u32) -> bool {
+        self.max_width.is_none_or(|max| width <= max)
+            && self.max_height().is_none_or(|max| height <= max)
+            && self
+                .max_area
+                .is_none_or(|max| u64::from(width) * u64::from(height) <= max)
+    }
+
+    /// 保持宽高比，计算限制范围内 `width` x `height` 能缩放到的最大尺寸
+    ///
+    /// Scales `width` x `height`, keeping the aspect ratio, to the largest size permitted
+    /// by the limits. Without limits the size is returned unchanged.
+    pub fn fit(&self, width: u32, height: u32) -> (u32, u32) {
+        if self.is_unlimited() {
+            return (width, height);
+        }
+        let mut ratio = f64::INFINITY;
+        if let Some(max) = self.max_width {
+            ratio = ratio.min(f64::from(max) / f64::from(width));
+        }
+        if let Some(max) = self.max_height() {
+            ratio = ratio.min(f64::from(max) / f64::from(height));
+        }
+        if let Some(max) = self.max_area {
+            ratio = ratio.min((max as f64 / (f64::from(width) * f64::from(height))).sqrt());
+        }
+        let mut w = ((f64::from(width) * ratio).floor() as u32).max(1);
+        let mut h = ((f64::from(height) * ratio).floor() as u32).max(1);
+        // 浮点误差可能导致面积略微超出
+        while !self.allows(w, h) && w > 1 && h > 1 {
+            w -= 1;
+            h = ((f64::from(height) * f64::from(w) / f64::from(width)).floor() as u32).max(1);
+        }
+        (w, h)
+    }
+}
+
+/// 像素矩形区域
+///
+/// A rectangle in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// 图片处理计划：请求解析后得到的精确几何信息
+///
+/// The exact geometry a request resolves to, computed from the source dimensions
+/// without decoding any pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImagePlan {
+    /// 原图尺寸
+    ///
+    /// The dimensions of the source image.
+    pub source_width: u32,
+    pub source_height: u32,
+
+    /// 在原图中裁剪的区域
+    ///
+    /// The crop rectangle in source coordinates.
+    pub crop: Rect,
+
+    /// 缩放后的尺寸
+    ///
+    /// The size of the extracted region after scaling.
+    pub scaled_width: u32,
+    pub scaled_height: u32,
+
+    /// 旋转后的外接矩形尺寸，即最终输出的像素尺寸
+    ///
+    /// The bounding box after rotation, i.e. the final pixel dimensions of the output.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ImagePlan {
+    /// 是否需要缩放
+    pub fn needs_resize(&self) -> bool {
+        (self.scaled_width, self.scaled_height) != (self.crop.width, self.crop.height)
+    }
+
+    /// 是否裁剪了完整图像
+    pub fn is_full_region(&self) -> bool {
+        self.crop == Rect::new(0, 0, self.source_width, self.source_height)
+    }
+
+    /// 解码时可用的最大缩小倍数（1、2、4 或 8），缩小后的区域仍不小于缩放目标
+    ///
+    /// The largest decode-time reduction (1, 2, 4 or 8) that keeps the reduced crop at least
+    /// as large as the scaled size, so only downscaling remains after decoding.
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::image::{IiifImage, Limits};
+    /// use url::Url;
+    ///
+    /// let url = Url::parse("https://example.org/iiif/demo.jpg/full/!200,200/0/default.jpg").unwrap();
+    /// let image = IiifImage::try_from(url).unwrap();
+    /// assert_eq!(image.plan(9000, 6000, &Limits::default()).unwrap().decode_scale(), 8);
+    /// assert_eq!(image.plan(600, 400, &Limits::default()).unwrap().decode_scale(), 2);
+    /// assert_eq!(image.plan(300, 200, &Limits::default()).unwrap().decode_scale(), 1);
+    /// ```
+    pub fn decode_scale(&self) -> u32 {
+        [8, 4, 2]
+            .into_iter()
+            .find(|d| {
+                self.crop.width / d >= self.scaled_width
+                    && self.crop.height / d >= self.scaled_height
+            })
+            .unwrap_or(1)
+    }
+
+    /// 原图按 1/`denominator` 解码后对应的处理计划，缩放和输出尺寸不变
+    ///
+    /// The plan for a source decoded at 1/`denominator` of its size: the source dimensions and
+    /// crop are reduced, the scaled and output dimensions are unchanged.
+    pub fn downscaled(&self, denominator: u32) -> ImagePlan {
+        let source_width = self.source_width.div_ceil(denominator);
+        let source_height = self.source_height.div_ceil(denominator);
+        let crop = self.crop;
+        let x = crop.x / denominator;
+        let y = crop.y / denominator;
+        let right = (crop.x + crop.width)
+            .div_ceil(denominator)
+            .min(source_width);
+        let bottom = (crop.y + crop.height)
+            .div_ceil(denominator)
+            .min(source_height);
+        ImagePlan {
+            source_width,
+            source_height,
+            crop: Rect::new(x, y, right - x, bottom - y),
+            ..*self
+        }
+    }
+}
+
+impl Size {
+    /// 在服务端尺寸限制下计算缩放后的尺寸，返回 (w, h)
+    ///
+    /// Calculates the scaled size of an extracted region of `width` x `height` pixels under
+    /// the given limits. `max` is constrained to the limits, `^max` is scaled up to them, and
+    /// any other size exceeding the limits is rejected.
+    pub fn resolve(
+        &self,
+        width: u32,
+        height: u32,
+        limits: &Limits,
+    ) -> Result<(u32, u32), IiifError> {
+        let (w, h) = match self {
+            Size::Max => {
+                if limits.allows(width, height) {
+                    (width, height)
+                } else {
+                    limits.fit(width, height)
+                }
+            }
+            Size::CMax => limits.fit(width, height),
+            _ => self.get_size(width, height)?,
+        };
+        if w == 0 || h == 0 {
+            return Err(IiifError::BadRequest(format!(
+                "Requested size results in an empty image: {self}"
+            )));
+        }
+        if !limits.allows(w, h) {
+            return Err(IiifError::NotFound(format!(
+                "Requested size {w}x{h} is greater than the limits"
+            )));
+        }
+        Ok((w, h))
+    }
+}
+
+impl Rotation {
+    /// 计算旋转后外接矩形的尺寸，返回 (w, h)
+    ///
+    /// Calculates the bounding box of a `width` x `height` image after rotation, returns (w, h).
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::image::Rotation;
+    ///
+    /// assert_eq!(Rotation::Degrees(90.0).get_size(300, 200).unwrap(), (200, 300));
+    /// assert_eq!(Rotation::Degrees(22.5).get_size(300, 200).unwrap(), (354, 300));
+    /// ```
+    pub fn get_size(&self, width: u32, height: u32) -> Result<(u32, u32), IiifError> {
+        let angle = self.angle();
+        if !(0.0..=360.0).contains(&angle) {
+            return Err(IiifError::BadRequest(
+                "Rotation angle is out of range".to_string(),
+            ));
+        }
+        Ok(super::rotation::rotated_size(width, height, angle))
+    }
+
+    /// 旋转角度
+    pub fn angle(&self) -> f32 {
+        match self {
+            Rotation::Degrees(angle) | Rotation::MirrorDegrees(angle) => *angle,
+        }
+    }
+
+    /// 是否镜像
+    pub fn is_mirror(&self) -> bool {
+        matches!(self, Rotation::MirrorDegrees(_))
+    }
+}
+
+impl IiifImage {
+    /// 根据原图尺寸和服务端限制计算处理计划，不进行任何解码
+    ///
+    /// Resolves the request against a `width` x `height` source and the service limits,
+    /// returning the exact crop rectangle, scaled size and output dimensions, or the
+    /// `IiifError` the request would fail with.
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::image::{IiifImage, Limits, Rect};
+    /// use url::Url;
+    ///
+    /// let url = Url::parse("https://example.org/iiif/demo.jpg/square/!100,100/90/default.jpg").unwrap();
+    /// let image = IiifImage::try_from(url).unwrap();
+    /// let plan = image.plan(300, 200, &Limits::default()).unwrap();
+    /// assert_eq!(plan.crop, Rect::new(50, 0, 200, 200));
+    /// assert_eq!((plan.scaled_width, plan.scaled_height), (100, 100));
+    /// assert_eq!((plan.width, plan.height), (100, 100));
+    /// ```
+    pub fn plan(&self, width: u32, height: u32, limits: &Limits) -> Result<ImagePlan, IiifError> {
+        if width == 0 || height == 0 {
+            return Err(IiifError::InternalServerError(
+                "Source image is empty".to_string(),
+            ));
+        }
+        let (x, y, w, h) = self.region.get_region(width, height)?;
+        if w == 0 || h == 0 {
+            return Err(IiifError::BadRequest(format!(
+                "Region is empty: {}",
+                self.region
+            )));
+        }
+        let (scaled_width, scaled_height) = self.size.resolve(w, h, limits)?;
+        let (out_width, out_height) = self.rotation.get_size(scaled_width, scaled_height)?;
+        Ok(ImagePlan {
+            source_width: width,
+            source_height: height,
+            crop: Rect::new(x, y, w, h),
+            scaled_width,
+            scaled_height,
+            width: out_width,
+            height: out_height,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::image::Region;
+    use crate::storage::{LocalStorage, Storage};
+
+    use super::*;
+
+    fn parse(request: &str) -> IiifImage {
+        let url = Url::parse(&format!("https://example.org/iiif/demo.jpg/{request}")).unwrap();
+        IiifImage::try_from(url).unwrap()
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = Limits {
+            max_width: Some(100),
+            ..Default::default()
+        };
+        assert!(limits.allows(100, 100));
+        assert!(!limits.allows(100, 101));
+        assert_eq!(limits.fit(300, 200), (100, 66));
+
+        let limits = Limits {
+            max_area: Some(6000),
+            ..Default::default()
+        };
+        assert!(!limits.allows(300, 200));
+        let (w, h) = limits.fit(300, 200);
+        assert!(w * h <= 6000);
+        assert_eq!((w, h), (94, 63));
+        assert!(Limits::default().allows(u32::MAX, u32::MAX));
+    }
+
+    #[test]
+    fn test_plan_matches_process() {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let origin = storage.get_origin_file("demo.jpg").unwrap();
+        let cases = [
+            "full/max/0/default.jpg",
+            "square/150,/15/color.png",
+            "125,15,200,200/!100,100/90/default.jpg",
+            "pct:41.6,7.5,40,70/^pct:120/!180/default.jpg",
+            "full/^360,/22.5/default.jpg",
+            "full/,150/135/default.jpg",
+        ];
+        for request in cases {
+            let params = parse(request);
+            let plan = params.plan(300, 200, &Limits::default()).unwrap();
+            let image = image::load_from_memory(&origin).unwrap();
+            let image = params.region.process(image).unwrap();
+            assert_eq!(
+                (image.width(), image.height()),
+                (plan.crop.width, plan.crop.height)
+            );
+            let image = params.size.process(image).unwrap();
+            assert_eq!(
+                (image.width(), image.height()),
+                (plan.scaled_width, plan.scaled_height)
+            );
+            let image = params.rotation.process(image).unwrap();
+            assert_eq!((image.width(), image.height()), (plan.width, plan.height));
+        }
+    }
+
+    #[test]
+    fn test_plan_limits() {
+        let limits = Limits {
+            max_width: Some(150),
+            max_height: Some(150),
+            max_area: None,
+        };
+        let plan = parse("full/max/0/default.jpg")
+            .plan(300, 200, &limits)
+            .unwrap();
+        assert_eq!((plan.width, plan.height), (150, 100));
+        let plan = parse("square/^max/0/default.jpg").plan(
+            300,
+            200,
+            &Limits {
+                max_width: Some(400),
+                ..Default::default()
+            },
+        );
+        assert_eq!(plan.unwrap().scaled_width, 400);
+
+        let result = parse("full/200,/0/default.jpg").plan(300, 200, &limits);
+        assert!(matches!(result, Err(IiifError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_plan_downscaled() {
+        let plan = parse("125,15,150,150/!30,30/0/default.jpg")
+            .plan(300, 200, &Limits::default())
+            .unwrap();
+        assert_eq!(plan.decode_scale(), 4);
+        let reduced = plan.downscaled(4);
+        assert_eq!((reduced.source_width, reduced.source_height), (75, 50));
+        assert_eq!(reduced.crop, Rect::new(31, 3, 38, 39));
+        assert_eq!(
+            (
+                reduced.scaled_width,
+                reduced.scaled_height,
+                reduced.width,
+                reduced.height
+            ),
+            (
+                plan.scaled_width,
+                plan.scaled_height,
+                plan.width,
+                plan.height
+            )
+        );
+
+        // 完整区域缩小后仍是完整区域
+        let plan = parse("full/!20,20/0/default.jpg")
+            .plan(301, 201, &Limits::default())
+            .unwrap();
+        assert!(plan.downscaled(8).is_full_region());
+        let plan = parse("full/max/0/default.jpg")
+            .plan(300, 200, &Limits::default())
+            .unwrap();
+        assert_eq!(plan.decode_scale(), 1);
+    }
+
+    #[test]
+    fn test_plan_error() {
+        let limits = Limits::default();
+        assert!(
+            parse("full/500,/0/default.jpg")
+                .plan(300, 200, &limits)
+                .is_err()
+        );
+        assert!(
+            parse("300,0,10,10/max/0/default.jpg")
+                .plan(300, 200, &limits)
+                .is_err()
+        );
+        assert!(
+            parse("full/max/361/default.jpg")
+                .plan(300, 200, &limits)
+                .is_err()
+        );
+        assert!(parse("full/max/0/default.jpg").plan(0, 0, &limits).is_err());
+
+        let params = IiifImage {
+            region: Region::Pct(0.0, 0.0, 0.1, 0.1),
+            ..parse("full/max/0/default.jpg")
+        };
+        assert!(params.plan(300, 200, &limits).is_err());
+    }
+}
@@ -0,0 +1,170 @@
+use crate::image::{Limits, SizeInfo, TileInfo};
+
+/// 生成 `info.json` 中 `sizes` 和 `tiles` 的规则
+///
+/// The rules used to compute the recommended `sizes` and `tiles` of the image information
+/// from the image dimensions.
+///
+/// - `sizes`: the full size halved repeatedly, smallest first, down to `min_size` pixels on
+///   the shorter edge, keeping only sizes within the service limits
+/// - `tiles`: one entry of `tile_width` x `tile_height` with scale factors `1, 2, 4, ...` up
+///   to the level at which the whole image fits in a single tile, at most 128
+///
+/// Example:
+/// ```
+/// use i3f::image::{Limits, PyramidConfig};
+///
+/// let config = PyramidConfig::default();
+/// let sizes = config.sizes(3000, 2000, &Limits::default());
+/// let sizes = sizes.iter().map(|s| (s.width, s.height)).collect::<Vec<_>>();
+/// assert_eq!(sizes[0], (188, 125));
+/// assert_eq!(sizes.last(), Some(&(3000, 2000)));
+///
+/// let tiles = config.tiles(3000, 2000);
+/// assert_eq!(tiles[0].scale_factors, vec![1, 2, 4, 8]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PyramidConfig {
+    /// 瓦片宽度
+    ///
+    /// The width of the tiles.
+    pub tile_width: u32,
+
+    /// 瓦片高度，默认与宽度相同
+    ///
+    /// The height of the tiles, square tiles if not set.
+    pub tile_height: Option<u32>,
+
+    /// `sizes` 中最小尺寸的短边长度下限
+    ///
+    /// The shortest edge of the smallest size listed in `sizes`.
+    pub min_size: u32,
+}
+
+impl Default for PyramidConfig {
+    fn default() -> Self {
+        Self {
+            tile_width: 512,
+            tile_height: None,
+            min_size: 64,
+        }
+    }
+}
+
+impl PyramidConfig {
+    /// 计算推荐的尺寸列表，从小到大排列
+    ///
+    /// Computes the recommended sizes of a `width` x `height` image, smallest first.
+    pub fn sizes(&self, width: u32, height: u32, limits: &Limits) -> Vec<SizeInfo> {
+        let mut sizes = Vec::new();
+        let mut factor = 1u32;
+        loop {
+            let w = width.div_ceil(factor);
+            let h = height.div_ceil(factor);
+            if limits.allows(w, h) {
+                sizes.push(SizeInfo {
+                    r#type: None,
+                    width: w,
+                    height: h,
+                });
+            }
+            if w.min(h) / 2 < self.min_size.max(1) || factor >= 1 << 30 {
+                break;
+            }
+            factor *= 2;
+        }
+        sizes.reverse();
+        sizes
+    }
+
+    /// 计算瓦片信息，缩放因子一直到整幅图像可以放入单个瓦片为止，最大为 128
+    ///
+    /// Computes the tiles of a `width` x `height` image, with scale factors up to the level
+    /// at which the whole image fits in a single tile. Factors are capped at 128, the largest
+    /// power of two representable in `TileInfo::scale_factors`.
+    pub fn tiles(&self, width: u32, height: u32) -> Vec<TileInfo> {
+        let tile_width = self.tile_width.max(1);
+        let tile_height = self.tile_height.unwrap_or(tile_width).max(1);
+        let mut scale_factors = vec![1u8];
+        let mut factor = 1u8;
+        while (width.div_ceil(u32::from(factor)) > tile_width
+            || height.div_ceil(u32::from(factor)) > tile_height)
+            && factor < 128
+        {
+            factor *= 2;
+            scale_factors.push(factor);
+        }
+        vec![TileInfo {
+            r#type: None,
+            scale_factors,
+            width: tile_width,
+            height: self.tile_height,
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::ServiceConfig;
+
+    use super::*;
+
+    #[test]
+    fn test_sizes() {
+        let config = PyramidConfig {
+            min_size: 50,
+            ..Default::default()
+        };
+        let sizes = config
+            .sizes(300, 200, &Limits::default())
+            .iter()
+            .map(|size| (size.width, size.height))
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![(75, 50), (150, 100), (300, 200)]);
+
+        let limits = Limits {
+            max_width: Some(200),
+            ..Default::default()
+        };
+        let sizes = config.sizes(300, 200, &limits);
+        assert_eq!(sizes.last().unwrap().width, 150);
+
+        // 图像本身小于下限时只保留原尺寸
+        assert_eq!(config.sizes(40, 30, &Limits::default()).len(), 1);
+    }
+
+    #[test]
+    fn test_tiles() {
+        let config = PyramidConfig {
+            tile_width: 256,
+            tile_height: Some(128),
+            min_size: 64,
+        };
+        let tiles = config.tiles(1000, 700);
+        assert_eq!(tiles[0].scale_factors, vec![1, 2, 4, 8]);
+        assert_eq!((tiles[0].width, tiles[0].height), (256, Some(128)));
+        assert_eq!(config.tiles(100, 100)[0].scale_factors, vec![1]);
+
+        // 缩放因子最大为 128
+        let tiles = config.tiles(1 << 20, 1 << 20);
+        assert_eq!(tiles[0].scale_factors.last(), Some(&128));
+    }
+
+    #[test]
+    fn test_image_info() {
+        let config = ServiceConfig {
+            pyramid: Some(PyramidConfig {
+                tile_width: 128,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let info = config.image_info("https://example.org/iiif/demo.jpg", 300, 200);
+        assert_eq!(info.sizes.unwrap().len(), 2);
+        assert_eq!(info.tiles.unwrap()[0].scale_factors, vec![1, 2, 4]);
+
+        let info = ServiceConfig::level0().image_info("demo.jpg", 300, 200);
+        assert!(info.sizes.is_none());
+        assert!(info.tiles.is_none());
+    }
+}
@@ -4,6 +4,7 @@ use image::DynamicImage;
 use serde::{Deserialize, Serialize};
 
 use crate::IiifError;
+use crate::image::is_high_bit_depth;
 
 /// Quality 画质定义
 ///
@@ -17,7 +18,7 @@ use crate::IiifError;
 /// let quality_color: Quality = "color".parse().unwrap();
 /// assert_eq!(quality_color, Quality::Color);
 /// ```
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Quality {
     /// Format: `default`
@@ -75,11 +76,20 @@ impl Quality {
             Quality::Color => Ok(image),
             Quality::Gray => Ok(image.grayscale()),
             Quality::Bitonal => {
+                // 二值化处理：阈值设为170，大于阈值的为白色(255)，小于等于阈值的为黑色(0)
+                let threshold = 170u8;
+                // 高位深图像按 16 位灰度计算阈值，避免先截断为 8 位
+                if is_high_bit_depth(&image) {
+                    let gray_image = image.to_luma16();
+                    let threshold = threshold as u16 * 257;
+                    return Ok(DynamicImage::ImageLuma8(imageproc::map::map_pixels(
+                        &gray_image,
+                        |_x, _y, pixel| image::Luma([if pixel[0] > threshold { 255u8 } else { 0 }]),
+                    )));
+                }
+
                 // 先转换为灰度图
                 let gray_image = image.to_luma8();
-
-                // 二值化处理：阈值设为128，大于阈值的为白色(255)，小于等于阈值的为黑色(0)
-                let threshold = 170u8;
                 let binary_image = imageproc::map::map_pixels(&gray_image, |_x, _y, pixel| {
                     if pixel[0] > threshold {
                         image::Luma([255u8]) // 白色
@@ -150,4 +160,18 @@ mod tests {
             assert_eq!(processed_image.height(), case.2);
         }
     }
+
+    #[test]
+    fn test_quality_bitonal_16bit() {
+        // 43700 截断为 8 位后为 170，不超过阈值；按 16 位计算时超过阈值
+        let image = DynamicImage::ImageLuma16(image::ImageBuffer::from_fn(2, 1, |x, _| {
+            image::Luma([if x == 0 { 43700u16 } else { 43600 }])
+        }));
+        let result = Quality::Bitonal.process(image).unwrap().to_luma8();
+        assert_eq!(result.as_raw(), &vec![255, 0]);
+
+        let image = DynamicImage::ImageRgb16(image::ImageBuffer::new(2, 2));
+        let result = Quality::Gray.process(image).unwrap();
+        assert!(matches!(result, DynamicImage::ImageLuma16(_)));
+    }
 }
@@ -29,7 +29,7 @@ use std::str::FromStr;
 /// assert_eq!(region_rect, Region::Rect(125, 15, 120, 140));
 /// assert_eq!(region_pct, Region::Pct(41.6, 7.5, 66.6, 100.0));
 /// ```
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Region {
     /// The full image is returned, without any cropping.
     ///
@@ -157,7 +157,15 @@ impl Region {
     /// 获取裁剪的区域，返回 (x, y, w, h)
     ///
     /// Get the region to be cropped, return (x, y, w, h).
-    fn get_region(
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::image::Region;
+    ///
+    /// let region = Region::Square;
+    /// assert_eq!(region.get_region(300, 200).unwrap(), (50, 0, 200, 200));
+    /// ```
+    pub fn get_region(
         &self,
         width: u32,
         height: u32,
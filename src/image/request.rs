@@ -0,0 +1,296 @@
+use std::str::FromStr;
+
+use url::Url;
+
+use crate::IiifError;
+use crate::image::{ApiVersion, Feature, IiifImage, Quality, ServiceConfig};
+
+/// 按 URI 语法区分的图像服务请求
+///
+/// A request to an image service, distinguished according to the
+/// [URI Syntax](https://iiif.io/api/image/3.0/#2-uri-syntax).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageRequest {
+    /// `{prefix}/{identifier}/{region}/{size}/{rotation}/{quality}.{format}`
+    ///
+    /// 图像请求
+    Image(IiifImage),
+
+    /// `{prefix}/{identifier}/info.json`
+    ///
+    /// 图像信息请求
+    Info { identifier: String },
+
+    /// `{prefix}/{identifier}`
+    ///
+    /// 图像的基础 URI，应重定向到 `info.json`
+    Base { identifier: String },
+}
+
+impl ImageRequest {
+    /// 请求的图像标识符
+    pub fn identifier(&self) -> &str {
+        match self {
+            ImageRequest::Image(image) => &image.identifier,
+            ImageRequest::Info { identifier } | ImageRequest::Base { identifier } => identifier,
+        }
+    }
+}
+
+/// 配置了服务前缀的请求解析器
+///
+/// Parses raw request paths of an image service mounted under a known prefix. Paths outside
+/// the prefix are rejected, and everything between the prefix and the image parameters is
+/// taken as the identifier, so identifiers may contain unencoded `/`.
+///
+/// Example:
+/// ```
+/// use i3f::image::{ImageRequest, RequestParser};
+///
+/// let parser = RequestParser::new("https://example.org/iiif");
+/// let request = parser.parse("/iiif/books/page1.jpg/full/max/0/default.jpg").unwrap();
+/// assert_eq!(request.identifier(), "books/page1.jpg");
+///
+/// let request = parser.parse("/iiif/books/page1.jpg/info.json").unwrap();
+/// assert!(matches!(request, ImageRequest::Info { .. }));
+///
+/// assert!(parser.parse("/other/books/page1.jpg/info.json").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestParser {
+    prefix: String,
+    version: ApiVersion,
+    v2_upscaling: bool,
+}
+
+impl RequestParser {
+    /// 使用服务前缀创建解析器，可以是 `/iiif` 这样的路径，也可以是完整的基础 URI
+    ///
+    /// Creates a parser for a service prefix, given either as a path such as `/iiif` or as the
+    /// full base URI of the service.
+    pub fn new(prefix: &str) -> Self {
+        let path = match Url::parse(prefix) {
+            Ok(url) => url.path().to_string(),
+            Err(_) => prefix.to_string(),
+        };
+        let path = path.trim_matches('/');
+        let prefix = if path.is_empty() {
+            String::new()
+        } else {
+            format!("/{path}")
+        };
+        Self {
+            prefix,
+            version: ApiVersion::default(),
+            v2_upscaling: false,
+        }
+    }
+
+    /// 指定请求使用的 API 版本
+    pub fn with_version(mut self, version: ApiVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// 按服务能力解析 2.x 请求：服务支持放大（`sizeAboveFull`）时，`w,`、`,h`、`w,h` 和
+    /// `!w,h` 解析为允许放大的 `^` 形式
+    ///
+    /// Parses 2.x requests for a service with the given capabilities. When the service
+    /// supports `sizeAboveFull`, the `w,`, `,h`, `w,h` and `!w,h` sizes may upscale and are
+    /// parsed into their `^` forms, see [`Size::v2_upscaled`](crate::image::Size::v2_upscaled).
+    pub fn with_service(mut self, config: &ServiceConfig) -> Self {
+        self.v2_upscaling = config.supports(&Feature::SizeUpscaling);
+        self
+    }
+
+    /// 服务前缀，以 `/` 开头且不含结尾的 `/`
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// 解析请求路径，查询参数和片段会被忽略
+    ///
+    /// Parses a raw request path. Any query string or fragment is ignored.
+    pub fn parse(&self, path: &str) -> Result<ImageRequest, IiifError> {
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let rest = path
+            .strip_prefix(&self.prefix)
+            .and_then(|rest| rest.strip_prefix('/'))
+            .ok_or_else(|| {
+                IiifError::NotFound(format!(
+                    "Path is not under the prefix {}: {path}",
+                    self.prefix
+                ))
+            })?;
+        let segments = rest.split('/').collect::<Vec<_>>();
+        let count = segments.len();
+
+        if segments[count - 1] == "info.json" {
+            let identifier = decode_identifier(&segments[..count - 1])?;
+            return Ok(ImageRequest::Info { identifier });
+        }
+        if count >= 5 && self.is_quality_format(segments[count - 1]) {
+            let identifier = decode_identifier(&segments[..count - 4])?;
+            let mut image =
+                IiifImage::from_params(identifier, &segments[count - 4..], self.version)?;
+            if self.version == ApiVersion::V2 && self.v2_upscaling {
+                image.size = image.size.v2_upscaled();
+            }
+            return Ok(ImageRequest::Image(image));
+        }
+        let identifier = decode_identifier(segments.strip_suffix(&[""]).unwrap_or(&segments))?;
+        Ok(ImageRequest::Base { identifier })
+    }
+
+    /// 解析完整的请求 URL
+    pub fn parse_url(&self, url: &Url) -> Result<ImageRequest, IiifError> {
+        self.parse(url.path())
+    }
+
+    // 最后一段是否形如 `{quality}.{format}`
+    fn is_quality_format(&self, segment: &str) -> bool {
+        let Some((quality, _)) = segment.split_once('.') else {
+            return false;
+        };
+        match self.version {
+            ApiVersion::V2 => Quality::from_v2_str(quality).is_ok(),
+            ApiVersion::V3 => Quality::from_str(quality).is_ok(),
+        }
+    }
+}
+
+// 将各段分别解码后以 `/` 连接为标识符
+fn decode_identifier(segments: &[&str]) -> Result<String, IiifError> {
+    let identifier = segments
+        .iter()
+        .map(|segment| super::url_decode(segment))
+        .collect::<Result<Vec<_>, _>>()?
+        .join("/");
+    if identifier.is_empty() {
+        return Err(IiifError::InvalidIiifURL(
+            "Identifier cannot be empty".to_string(),
+        ));
+    }
+    Ok(identifier)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::{Region, Size};
+
+    use super::*;
+
+    #[test]
+    fn test_parse_image() {
+        let parser = RequestParser::new("/iiif/");
+        assert_eq!(parser.prefix(), "/iiif");
+
+        let request = parser
+            .parse("/iiif/demo.jpg/square/max/90/gray.png?foo=bar")
+            .unwrap();
+        let ImageRequest::Image(image) = request else {
+            panic!("expected an image request");
+        };
+        assert_eq!(image.identifier, "demo.jpg");
+        assert_eq!(image.region, Region::Square);
+        assert_eq!(image.to_string(), "demo.jpg/square/max/90/gray.png");
+
+        let request = parser
+            .parse("/iiif/a/b%2Fc/demo.jpg/full/max/0/default.jpg")
+            .unwrap();
+        assert_eq!(request.identifier(), "a/b/c/demo.jpg");
+
+        // 参数错误不会被当作标识符的一部分
+        assert!(
+            parser
+                .parse("/iiif/demo.jpg/full/max/abc/default.jpg")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_info_and_base() {
+        let parser = RequestParser::new("https://example.org/iiif");
+        assert_eq!(
+            parser.parse("/iiif/books/page1/info.json").unwrap(),
+            ImageRequest::Info {
+                identifier: "books/page1".to_string()
+            }
+        );
+        assert_eq!(
+            parser.parse("/iiif/books/page1/").unwrap(),
+            ImageRequest::Base {
+                identifier: "books/page1".to_string()
+            }
+        );
+        assert_eq!(
+            parser.parse("/iiif/ark:%2F12025%2F654").unwrap(),
+            ImageRequest::Base {
+                identifier: "ark:/12025/654".to_string()
+            }
+        );
+        assert!(parser.parse("/iiif/info.json").is_err());
+        assert!(parser.parse("/iiif/").is_err());
+    }
+
+    #[test]
+    fn test_parse_prefix() {
+        let parser = RequestParser::new("/iiif");
+        for path in ["/demo.jpg/info.json", "/iiif2/demo.jpg/info.json", "/iiif"] {
+            assert!(matches!(parser.parse(path), Err(IiifError::NotFound(_))));
+        }
+
+        let parser = RequestParser::new("");
+        assert_eq!(
+            parser.parse("/demo.jpg/info.json").unwrap().identifier(),
+            "demo.jpg"
+        );
+    }
+
+    #[test]
+    fn test_parse_v2() {
+        let parser = RequestParser::new("/iiif/2").with_version(ApiVersion::V2);
+        let request = parser
+            .parse("/iiif/2/demo.jpg/full/full/0/native.jpg")
+            .unwrap();
+        let ImageRequest::Image(image) = request else {
+            panic!("expected an image request");
+        };
+        assert_eq!(image.size, Size::Max);
+    }
+
+    #[test]
+    fn test_parse_v2_upscaling() {
+        let parse = |parser: &RequestParser, path: &str| match parser.parse(path).unwrap() {
+            ImageRequest::Image(image) => image.size,
+            request => panic!("expected an image request: {request:?}"),
+        };
+        let v2 = RequestParser::new("/iiif/2").with_version(ApiVersion::V2);
+        let cases = [
+            ("600,", Size::W { w: 600 }, Size::CW { w: 600 }),
+            (",400", Size::H { h: 400 }, Size::CH { h: 400 }),
+            (
+                "600,400",
+                Size::WH { w: 600, h: 400 },
+                Size::CWH { w: 600, h: 400 },
+            ),
+            (
+                "!600,400",
+                Size::LWH { w: 600, h: 400 },
+                Size::CLWH { w: 600, h: 400 },
+            ),
+        ];
+        for (size, level2, upscaling) in cases {
+            let path = format!("/iiif/2/demo.jpg/full/{size}/0/default.jpg");
+            let parser = v2.clone().with_service(&ServiceConfig::level2());
+            assert_eq!(parse(&parser, &path), level2, "{size}");
+            let parser = v2.clone().with_service(&ServiceConfig::default());
+            assert_eq!(parse(&parser, &path), upscaling, "{size}");
+        }
+
+        // 3.0 请求必须显式使用 `^`
+        let v3 = RequestParser::new("/iiif/3").with_service(&ServiceConfig::default());
+        let size = parse(&v3, "/iiif/3/demo.jpg/full/600,/0/default.jpg");
+        assert_eq!(size, Size::W { w: 600 });
+    }
+}
@@ -1,5 +1,5 @@
 /// iiif 处理结果
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ProcessResult {
     pub content_type: String,
     pub data: Vec<u8>,
@@ -1,9 +1,10 @@
 use std::{fmt::Display, str::FromStr};
 
-use image::DynamicImage;
-use image::GenericImageView;
+use image::{DynamicImage, ImageBuffer, Pixel, Primitive, Rgba};
+use imageproc::definitions::Clamp;
 
 use crate::IiifError;
+use crate::image::is_high_bit_depth;
 
 /// Rotation 旋转角度定义
 ///
@@ -19,7 +20,7 @@ use crate::IiifError;
 /// let ratation: Rotation = "45.5".parse().unwrap();
 /// assert_eq!(ratation, Rotation::Degrees(45.5));
 /// ```
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Rotation {
     /// Format: `n`
     /// The degrees of clockwise rotation from 0 up to 360.
@@ -80,30 +81,53 @@ fn standard_rotate(image: DynamicImage, angle: f32) -> DynamicImage {
     }
 }
 
+/// 计算 `width` x `height` 的图片旋转 `angle` 度后外接矩形的尺寸
+pub(crate) fn rotated_size(width: u32, height: u32, angle: f32) -> (u32, u32) {
+    if is_multiple_of_90(angle) {
+        return if (angle / 90.0) as u32 % 2 == 1 {
+            (height, width)
+        } else {
+            (width, height)
+        };
+    }
+    let radians = angle.to_radians();
+    let (sin, cos) = (radians.sin().abs(), radians.cos().abs());
+    let new_width = (width as f32 * cos + height as f32 * sin).round() as u32;
+    let new_height = (width as f32 * sin + height as f32 * cos).round() as u32;
+    (new_width, new_height)
+}
+
 fn rotate(image: DynamicImage, angle: f32) -> DynamicImage {
+    // 高位深图像使用 16 位画布，避免降低精度
+    if is_high_bit_depth(&image) {
+        DynamicImage::ImageRgba16(rotate_buffer(&image.to_rgba16(), angle))
+    } else {
+        DynamicImage::ImageRgba8(rotate_buffer(&image.to_rgba8(), angle))
+    }
+}
+
+fn rotate_buffer<S>(
+    image: &ImageBuffer<Rgba<S>, Vec<S>>,
+    angle: f32,
+) -> ImageBuffer<Rgba<S>, Vec<S>>
+where
+    Rgba<S>: Pixel<Subpixel = S>,
+    S: Primitive + Send + Sync + Into<f32> + Clamp<f32>,
+{
+    // 计算旋转后的图片大小
+    let (new_width, new_height) = rotated_size(image.width(), image.height(), angle);
     // 旋转角度转换为弧度
     let angle = angle * std::f32::consts::PI / 180.0;
-    // 计算旋转后的图片大小
-    let new_width =
-        (image.width() as f32 * angle.cos() + image.height() as f32 * angle.sin()).round() as u32;
-    let new_height =
-        (image.width() as f32 * angle.sin() + image.height() as f32 * angle.cos()).round() as u32;
-    let mut rotated_image = image::ImageBuffer::new(new_width, new_height);
-    for x in 0..image.width() {
-        for y in 0..image.height() {
-            let new_x = x + ((new_width as f32 - image.width() as f32) / 2.0).round() as u32;
-            let new_y = y + ((new_height as f32 - image.height() as f32) / 2.0).round() as u32;
-            let pixel = image.get_pixel(x, y);
-            rotated_image.put_pixel(new_x, new_y, pixel);
-        }
-    }
-    let rotated_image = imageproc::geometric_transformations::rotate_about_center(
+    let mut rotated_image = ImageBuffer::new(new_width, new_height);
+    let offset_x = ((new_width as f32 - image.width() as f32) / 2.0).round() as i64;
+    let offset_y = ((new_height as f32 - image.height() as f32) / 2.0).round() as i64;
+    image::imageops::replace(&mut rotated_image, image, offset_x, offset_y);
+    imageproc::geometric_transformations::rotate_about_center(
         &rotated_image,
         angle,
         imageproc::geometric_transformations::Interpolation::Bicubic,
-        image::Rgba([0, 0, 0, 0]),
-    );
-    image::DynamicImage::ImageRgba8(rotated_image)
+        Rgba([S::DEFAULT_MIN_VALUE; 4]),
+    )
 }
 
 impl FromStr for Rotation {
@@ -190,6 +214,8 @@ mod tests {
             ("!180", 300, 200),
             ("22.5", 354, 300),
             ("!22.5", 354, 300),
+            ("135", 354, 354),
+            ("!202.5", 354, 300),
         ];
         for case in cases {
             let rotation = case.0.parse::<Rotation>().unwrap();
@@ -215,4 +241,25 @@ mod tests {
         let result = rotation.process(image);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_rotation_process_16bit() {
+        let image = DynamicImage::ImageRgb16(image::ImageBuffer::from_pixel(
+            40,
+            20,
+            image::Rgb([1000u16, 30001, 65535]),
+        ));
+        let result = Rotation::Degrees(30.0).process(image.clone()).unwrap();
+        let DynamicImage::ImageRgba16(result) = result else {
+            panic!("expected a 16-bit image");
+        };
+        let (width, height) = result.dimensions();
+        assert_eq!(
+            result.get_pixel(width / 2, height / 2).0,
+            [1000, 30001, 65535, 65535]
+        );
+
+        let result = Rotation::MirrorDegrees(90.0).process(image).unwrap();
+        assert!(matches!(result, DynamicImage::ImageRgb16(_)));
+    }
 }
@@ -0,0 +1,630 @@
+use std::sync::Arc;
+
+use crate::IiifError;
+use crate::image::{
+    Backend, ColorManagement, Feature, Format, IiifImage, ImageBackend, ImageInfo, Limits, Profile,
+    PyramidConfig, Quality, Region, Rotation, Size, Source, TiledTiff, page_count,
+};
+use crate::pdf::PdfOptions;
+use crate::storage::Storage;
+
+/// 图像服务的能力配置，决定服务接受哪些请求以及 `info.json` 中声明的 `profile` 和 `extra_*`
+///
+/// The capabilities of an image service. The same configuration is used to reject requests
+/// the service does not support and to generate the `profile`, `extra*` and `max*` properties
+/// of the image information document, so advertised and actual behaviour cannot diverge.
+///
+/// Example:
+/// ```
+/// use i3f::image::{IiifImage, ServiceConfig};
+/// use url::Url;
+///
+/// let url = Url::parse("https://example.org/iiif/demo.jpg/pct:10,10,50,50/max/0/default.jpg").unwrap();
+/// let image = IiifImage::try_from(url).unwrap();
+/// assert!(ServiceConfig::level0().check(&image).is_err());
+/// assert!(ServiceConfig::level2().check(&image).is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ServiceConfig {
+    /// 服务完全支持的合规等级
+    ///
+    /// The compliance level fully supported by the service.
+    pub profile: Profile,
+
+    /// 合规等级之外额外支持的功能
+    ///
+    /// Features supported in addition to those of the compliance level.
+    pub extra_features: Vec<Feature>,
+
+    /// 合规等级之外额外支持的画质
+    ///
+    /// Qualities supported in addition to those of the compliance level.
+    pub extra_qualities: Vec<Quality>,
+
+    /// 合规等级之外额外支持的格式
+    ///
+    /// Formats supported in addition to those of the compliance level.
+    pub extra_formats: Vec<Format>,
+
+    /// 首选格式，按偏好顺序排列，支持这些格式的客户端优先使用
+    ///
+    /// The preferred formats in order of preference, used first by clients that support them.
+    pub preferred_formats: Vec<Format>,
+
+    /// 尺寸限制
+    ///
+    /// The size limits of the service.
+    pub limits: Limits,
+
+    /// 生成 `sizes` 和 `tiles` 的规则，未设置时不生成
+    ///
+    /// The rules for the generated `sizes` and `tiles`, none are generated if not set.
+    pub pyramid: Option<PyramidConfig>,
+
+    /// 加载原图时是否按 EXIF 方向信息转换为显示方向，关闭时保留原始传感器方向
+    ///
+    /// Whether sources are rotated to their display orientation according to the EXIF
+    /// Orientation tag, or kept in raw sensor orientation.
+    pub auto_orient: bool,
+
+    /// 原图嵌入 ICC 配置文件时的色彩管理策略
+    ///
+    /// How embedded ICC profiles of sources are handled.
+    pub color: ColorManagement,
+
+    /// PDF 输出的页面尺寸、文档信息和图像压缩方式
+    ///
+    /// The page size, document information and image compression of PDF outputs.
+    pub pdf: PdfOptions,
+
+    /// TIFF 输出的分块金字塔布局，未设置时输出不分块的单层 TIFF
+    ///
+    /// The tiled pyramidal layout of TIFF outputs, flat single-resolution TIFFs if not set.
+    pub tiff: Option<TiledTiff>,
+
+    /// 图像处理后端，默认为基于 `image` 的 [`ImageBackend`]
+    ///
+    /// The image-processing backend, [`ImageBackend`] by default.
+    pub backend: Arc<dyn Backend>,
+}
+
+impl PartialEq for ServiceConfig {
+    fn eq(&self, other: &Self) -> bool {
+        self.profile == other.profile
+            && self.extra_features == other.extra_features
+            && self.extra_qualities == other.extra_qualities
+            && self.extra_formats == other.extra_formats
+            && self.preferred_formats == other.preferred_formats
+            && self.limits == other.limits
+            && self.pyramid == other.pyramid
+            && self.auto_orient == other.auto_orient
+            && self.color == other.color
+            && self.pdf == other.pdf
+            && self.tiff == other.tiff
+            && self.backend.name() == other.backend.name()
+    }
+}
+
+impl Default for ServiceConfig {
+    /// 默认配置支持本库实现的全部功能
+    ///
+    /// The default configuration supports everything this library implements.
+    fn default() -> Self {
+        Self {
+            profile: Profile::Level2,
+            extra_features: vec![
+                Feature::Mirroring,
+                Feature::RotationArbitrary,
+                Feature::SizeUpscaling,
+            ],
+            extra_qualities: vec![Quality::Gray],
+            extra_formats: vec![
+                Format::Tif,
+                Format::Gif,
+                Format::Pdf,
+                Format::Webp,
+                #[cfg(feature = "avif")]
+                Format::Avif,
+                #[cfg(feature = "bmp")]
+                Format::Bmp,
+            ],
+            // AVIF 压缩率最高，启用时优先
+            preferred_formats: vec![
+                #[cfg(feature = "avif")]
+                Format::Avif,
+            ],
+            limits: Limits::default(),
+            pyramid: Some(PyramidConfig::default()),
+            auto_orient: true,
+            color: ColorManagement::default(),
+            pdf: PdfOptions::default(),
+            tiff: None,
+            backend: Arc::new(ImageBackend),
+        }
+    }
+}
+
+impl ServiceConfig {
+    /// 仅支持 `level0` 的服务
+    pub fn level0() -> Self {
+        Self::with_profile(Profile::Level0)
+    }
+
+    /// 仅支持 `level1` 的服务
+    pub fn level1() -> Self {
+        Self::with_profile(Profile::Level1)
+    }
+
+    /// 仅支持 `level2` 的服务
+    pub fn level2() -> Self {
+        Self::with_profile(Profile::Level2)
+    }
+
+    fn with_profile(profile: Profile) -> Self {
+        Self {
+            profile,
+            extra_features: Vec::new(),
+            extra_qualities: Vec::new(),
+            extra_formats: Vec::new(),
+            preferred_formats: Vec::new(),
+            limits: Limits::default(),
+            pyramid: None,
+            auto_orient: true,
+            color: ColorManagement::default(),
+            pdf: PdfOptions::default(),
+            tiff: None,
+            backend: Arc::new(ImageBackend),
+        }
+    }
+
+    /// 从图像信息中读取服务能力
+    ///
+    /// Reads the capabilities advertised by an image information document.
+    pub fn from_info(info: &ImageInfo) -> Self {
+        Self {
+            profile: info.profile.clone(),
+            extra_features: info.extra_features.clone().unwrap_or_default(),
+            extra_qualities: info.extra_qualities.clone().unwrap_or_default(),
+            extra_formats: info.extra_formats.clone().unwrap_or_default(),
+            preferred_formats: info.preferred_formats.clone().unwrap_or_default(),
+            limits: Limits::from_info(info),
+            pyramid: None,
+            auto_orient: true,
+            color: ColorManagement::default(),
+            pdf: PdfOptions::default(),
+            tiff: None,
+            backend: Arc::new(ImageBackend),
+        }
+    }
+
+    /// 是否支持某个功能
+    pub fn supports(&self, feature: &Feature) -> bool {
+        self.profile.features().contains(feature) || self.extra_features.contains(feature)
+    }
+
+    /// 是否支持某个画质
+    pub fn supports_quality(&self, quality: &Quality) -> bool {
+        self.profile.qualities().contains(quality) || self.extra_qualities.contains(quality)
+    }
+
+    /// 是否支持某个格式
+    pub fn supports_format(&self, format: &Format) -> bool {
+        self.profile.formats().contains(format) || self.extra_formats.contains(format)
+    }
+
+    /// 检查请求的参数是否受服务支持，不支持时返回 `IiifError::NotFound`
+    ///
+    /// Checks that every parameter of the request is supported by the service. Unsupported
+    /// values fail with `IiifError::NotFound`, as required by the specification. Widths and
+    /// heights given explicitly in the size are checked against the size limits here; the
+    /// limits that depend on the source dimensions are applied by [`IiifImage::plan`].
+    pub fn check(&self, image: &IiifImage) -> Result<(), IiifError> {
+        let region_feature = match image.region {
+            Region::Full => None,
+            Region::Square => Some(Feature::RegionSquare),
+            Region::Rect(..) => Some(Feature::RegionByPx),
+            Region::Pct(..) => Some(Feature::RegionByPct),
+        };
+        self.require(region_feature, "region", &image.region)?;
+
+        let size_feature = match image.size {
+            Size::Max | Size::CMax => None,
+            Size::W { .. } | Size::CW { .. } => Some(Feature::SizeByW),
+            Size::H { .. } | Size::CH { .. } => Some(Feature::SizeByH),
+            Size::Pct { .. } | Size::CPct { .. } => Some(Feature::SizeByPct),
+            Size::WH { .. } | Size::CWH { .. } => Some(Feature::SizeByWh),
+            Size::LWH { .. } | Size::CLWH { .. } => Some(Feature::SizeByConfinedWh),
+        };
+        self.require(size_feature, "size", &image.size)?;
+        if image.size.is_upscaling() {
+            self.require(Some(Feature::SizeUpscaling), "size", &image.size)?;
+        }
+
+        let angle = image.rotation.angle();
+        let rotation_feature = if angle % 360.0 == 0.0 {
+            None
+        } else if angle % 90.0 == 0.0 {
+            Some(Feature::RotationBy90s)
+        } else {
+            Some(Feature::RotationArbitrary)
+        };
+        self.require(rotation_feature, "rotation", &image.rotation)?;
+        if let Rotation::MirrorDegrees(_) = image.rotation {
+            self.require(Some(Feature::Mirroring), "rotation", &image.rotation)?;
+        }
+
+        if !self.supports_quality(&image.quality) {
+            return Err(IiifError::NotFound(format!(
+                "Quality is not supported: {}",
+                image.quality
+            )));
+        }
+        if !self.supports_format(&image.format) {
+            return Err(IiifError::NotFound(format!(
+                "Format is not supported: {}",
+                image.format
+            )));
+        }
+
+        // 尺寸中明确给出的宽高无需读取原图即可对照尺寸限制
+        let (w, h) = match image.size {
+            Size::W { w } | Size::CW { w } => (Some(w), None),
+            Size::H { h } | Size::CH { h } => (None, Some(h)),
+            Size::WH { w, h } | Size::CWH { w, h } => (Some(w), Some(h)),
+            _ => (None, None),
+        };
+        let limits = &self.limits;
+        let exceeds = w.zip(limits.max_width).is_some_and(|(w, max)| w > max)
+            || h.zip(limits.max_height()).is_some_and(|(h, max)| h > max)
+            || w.zip(h)
+                .zip(limits.max_area)
+                .is_some_and(|((w, h), max)| u64::from(w) * u64::from(h) > max);
+        if exceeds {
+            return Err(IiifError::NotFound(format!(
+                "Requested size {} is greater than the limits",
+                image.size
+            )));
+        }
+        Ok(())
+    }
+
+    fn require(
+        &self,
+        feature: Option<Feature>,
+        name: &str,
+        value: &dyn std::fmt::Display,
+    ) -> Result<(), IiifError> {
+        match feature {
+            Some(feature) if !self.supports(&feature) => Err(IiifError::NotFound(format!(
+                "The {name} is not supported: {value}"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// 影响衍生文件内容的全部配置，用于合并相同的并发请求
+    ///
+    /// Every setting that affects the generated derivatives, used to key identical
+    /// concurrent requests. The backend contributes its [`Backend::name`].
+    pub(crate) fn fingerprint(&self) -> String {
+        format!(
+            "{:?}/{:?}/{:?}/{:?}/{:?}/{}/{:?}/{:?}/{:?}/{}",
+            self.profile,
+            self.extra_features,
+            self.extra_qualities,
+            self.extra_formats,
+            self.limits,
+            self.auto_orient,
+            self.color,
+            self.pdf,
+            self.tiff,
+            self.backend.name()
+        )
+    }
+
+    /// 生成图像信息，填充 `profile`、`extra_*`、尺寸限制以及 `sizes` 和 `tiles`
+    ///
+    /// Creates the image information document of a `width` x `height` image served with
+    /// this configuration.
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::image::{Feature, Profile, ServiceConfig};
+    ///
+    /// let info = ServiceConfig::default().image_info("https://example.org/iiif/demo.jpg", 300, 200);
+    /// assert_eq!(info.profile, Profile::Level2);
+    /// assert!(info.extra_features.unwrap().contains(&Feature::Mirroring));
+    /// ```
+    pub fn image_info(&self, id: &str, width: u32, height: u32) -> ImageInfo {
+        fn non_empty<T: Clone>(values: &[T]) -> Option<Vec<T>> {
+            (!values.is_empty()).then(|| values.to_vec())
+        }
+        ImageInfo {
+            id: id.trim_end_matches('/').to_string(),
+            profile: self.profile.clone(),
+            width,
+            height,
+            max_width: self.limits.max_width,
+            max_height: self.limits.max_height,
+            max_area: self
+                .limits
+                .max_area
+                .map(|area| u32::try_from(area).unwrap_or(u32::MAX)),
+            extra_features: non_empty(&self.extra_features),
+            extra_qualities: non_empty(&self.extra_qualities),
+            extra_formats: non_empty(&self.extra_formats),
+            preferred_formats: non_empty(&self.preferred_formats),
+            sizes: self
+                .pyramid
+                .map(|pyramid| pyramid.sizes(width, height, &self.limits)),
+            tiles: self.pyramid.map(|pyramid| pyramid.tiles(width, height)),
+            ..Default::default()
+        }
+    }
+
+    /// 读取原图并生成每一页的图像信息
+    ///
+    /// Reads the source of `identifier` and generates the image information of each of its
+    /// pages, so the pages of multi-page TIFF, GIF and PDF sources can be enumerated. Page `n`
+    /// is served as `{identifier};page={n}` under `base_uri`, while a single-page source yields
+    /// one document for `identifier` itself.
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::image::ServiceConfig;
+    /// use i3f::storage::LocalStorage;
+    ///
+    /// let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+    /// let config = ServiceConfig::default();
+    /// let infos = config.page_infos(&storage, "https://example.org/iiif", "pages.tif").unwrap();
+    /// assert_eq!(infos.len(), 3);
+    /// assert_eq!(infos[1].id, "https://example.org/iiif/pages.tif%3Bpage%3D2");
+    /// assert_eq!((infos[1].width, infos[1].height), (60, 40));
+    /// ```
+    pub fn page_infos(
+        &self,
+        storage: &dyn Storage,
+        base_uri: &str,
+        identifier: &str,
+    ) -> Result<Vec<ImageInfo>, IiifError> {
+        let base_uri = base_uri.trim_end_matches('/');
+        let data = storage
+            .get_origin_file(identifier)
+            .map_err(IiifError::InternalServerError)?;
+        let count = page_count(&data)?;
+        (1..=count)
+            .map(|page| {
+                let (width, height) = Source::page(&data, page, self.auto_orient)?.dimensions();
+                let id = if count == 1 {
+                    super::url_encode(identifier)
+                } else {
+                    super::url_encode(&format!("{identifier};page={page}"))
+                };
+                Ok(self.image_info(&format!("{base_uri}/{id}"), width, height))
+            })
+            .collect()
+    }
+}
+
+impl Profile {
+    /// 合规等级要求支持的功能
+    ///
+    /// The features required by the compliance level.
+    pub fn features(&self) -> &'static [Feature] {
+        const LEVEL1: &[Feature] = &[
+            Feature::BaseUriRedirect,
+            Feature::Cors,
+            Feature::JsonldMediaType,
+            Feature::RegionByPx,
+            Feature::RegionSquare,
+            Feature::SizeByW,
+            Feature::SizeByH,
+            Feature::SizeByWh,
+        ];
+        const LEVEL2: &[Feature] = &[
+            Feature::BaseUriRedirect,
+            Feature::Cors,
+            Feature::JsonldMediaType,
+            Feature::RegionByPx,
+            Feature::RegionSquare,
+            Feature::RegionByPct,
+            Feature::SizeByW,
+            Feature::SizeByH,
+            Feature::SizeByWh,
+            Feature::SizeByConfinedWh,
+            Feature::SizeByPct,
+            Feature::RotationBy90s,
+        ];
+        match self {
+            Profile::Level0 => &[],
+            Profile::Level1 => LEVEL1,
+            Profile::Level2 => LEVEL2,
+        }
+    }
+
+    /// 合规等级要求支持的画质
+    ///
+    /// The qualities required by the compliance level.
+    pub fn qualities(&self) -> &'static [Quality] {
+        match self {
+            Profile::Level0 | Profile::Level1 => &[Quality::Default],
+            Profile::Level2 => &[Quality::Default, Quality::Color, Quality::Bitonal],
+        }
+    }
+
+    /// 合规等级要求支持的格式
+    ///
+    /// The formats required by the compliance level.
+    pub fn formats(&self) -> &'static [Format] {
+        match self {
+            Profile::Level0 | Profile::Level1 => &[Format::Jpg],
+            Profile::Level2 => &[Format::Jpg, Format::Png],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::*;
+
+    fn parse(request: &str) -> IiifImage {
+        let url = Url::parse(&format!("https://example.org/iiif/demo.jpg/{request}")).unwrap();
+        IiifImage::try_from(url).unwrap()
+    }
+
+    #[test]
+    fn test_check_levels() {
+        let cases = vec![
+            // (request, level0, level1, level2)
+            ("full/max/0/default.jpg", true, true, true),
+            ("square/max/0/default.jpg", false, true, true),
+            ("0,0,100,100/100,/0/default.jpg", false, true, true),
+            ("pct:10,10,50,50/max/0/default.jpg", false, false, true),
+            ("full/!100,100/0/default.jpg", false, false, true),
+            ("full/pct:50/0/default.jpg", false, false, true),
+            ("full/max/90/default.jpg", false, false, true),
+            ("full/max/360/default.jpg", true, true, true),
+            ("full/max/22.5/default.jpg", false, false, false),
+            ("full/max/!0/default.jpg", false, false, false),
+            ("full/^max/0/default.jpg", false, false, false),
+            ("full/^100,/0/default.jpg", false, false, false),
+            ("full/max/0/color.jpg", false, false, true),
+            ("full/max/0/gray.jpg", false, false, false),
+            ("full/max/0/default.png", false, false, true),
+            ("full/max/0/default.webp", false, false, false),
+        ];
+        let configs = [
+            ServiceConfig::level0(),
+            ServiceConfig::level1(),
+            ServiceConfig::level2(),
+        ];
+        for (request, level0, level1, level2) in cases {
+            let image = parse(request);
+            for (config, expected) in configs.iter().zip([level0, level1, level2]) {
+                let result = config.check(&image);
+                assert_eq!(result.is_ok(), expected, "{request} {:?}", config.profile);
+                if let Err(e) = result {
+                    assert!(matches!(e, IiifError::NotFound(_)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_limits() {
+        let config = ServiceConfig {
+            limits: Limits {
+                max_width: Some(200),
+                max_height: None,
+                max_area: Some(30_000),
+            },
+            ..Default::default()
+        };
+        let cases = [
+            ("full/200,/0/default.jpg", true),
+            ("full/201,/0/default.jpg", false),
+            ("full/^,201/0/default.jpg", false),
+            ("full/200,150/0/default.jpg", true),
+            ("full/200,151/0/default.jpg", false),
+            // 依赖原图尺寸的限制在读取原图后检查
+            ("full/!1000,1000/0/default.jpg", true),
+            ("full/pct:50/0/default.jpg", true),
+            ("full/max/0/default.jpg", true),
+        ];
+        for (request, expected) in cases {
+            let result = config.check(&parse(request));
+            assert_eq!(result.is_ok(), expected, "{request}");
+            if let Err(e) = result {
+                assert!(matches!(e, IiifError::NotFound(_)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_extras() {
+        let config = ServiceConfig::default();
+        for request in [
+            "full/max/22.5/default.jpg",
+            "full/max/!90/gray.webp",
+            "full/^150,/0/default.tif",
+        ] {
+            assert!(config.check(&parse(request)).is_ok(), "{request}");
+        }
+        assert!(config.check(&parse("full/max/0/default.jp2")).is_err());
+    }
+
+    #[cfg(feature = "avif")]
+    #[test]
+    fn test_preferred_formats() {
+        let config = ServiceConfig::default();
+        assert!(config.check(&parse("full/max/0/default.avif")).is_ok());
+        let info = config.image_info("https://example.org/iiif/demo.jpg", 300, 200);
+        assert_eq!(info.preferred_formats, Some(vec![Format::Avif]));
+        assert!(info.extra_formats.unwrap().contains(&Format::Avif));
+        let json = serde_json::to_value(ServiceConfig::default().image_info("demo.jpg", 1, 1));
+        assert_eq!(
+            json.unwrap()["preferredFormats"],
+            serde_json::json!(["avif"])
+        );
+    }
+
+    #[test]
+    fn test_image_info_round_trip() {
+        let config = ServiceConfig {
+            limits: Limits {
+                max_width: Some(1000),
+                max_height: None,
+                max_area: Some(500_000),
+            },
+            pyramid: None,
+            ..Default::default()
+        };
+        let info = config.image_info("https://example.org/iiif/demo.jpg/", 300, 200);
+        assert_eq!(info.id, "https://example.org/iiif/demo.jpg");
+        assert_eq!(info.max_width, Some(1000));
+        assert_eq!(info.max_area, Some(500_000));
+        assert_eq!(ServiceConfig::from_info(&info), config);
+
+        let info = ServiceConfig::level1().image_info("demo.jpg", 300, 200);
+        assert_eq!(info.profile, Profile::Level1);
+        assert!(info.extra_features.is_none());
+        assert!(info.extra_formats.is_none());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let config = ServiceConfig::default();
+        assert_eq!(config.fingerprint(), ServiceConfig::default().fingerprint());
+        let preferred = ServiceConfig {
+            preferred_formats: vec![],
+            pyramid: None,
+            ..Default::default()
+        };
+        assert_eq!(config.fingerprint(), preferred.fingerprint());
+
+        let differing = [
+            ServiceConfig {
+                color: ColorManagement::Preserve,
+                ..Default::default()
+            },
+            ServiceConfig {
+                auto_orient: false,
+                ..Default::default()
+            },
+            ServiceConfig {
+                extra_qualities: vec![],
+                ..Default::default()
+            },
+            ServiceConfig {
+                extra_formats: vec![],
+                ..Default::default()
+            },
+            ServiceConfig::level1(),
+        ];
+        for other in differing {
+            assert_ne!(config.fingerprint(), other.fingerprint(), "{other:?}");
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use std::{fmt::Display, str::FromStr};
 
-use image::{DynamicImage, imageops::FilterType};
+use image::DynamicImage;
 
 use crate::IiifError;
+use crate::image::resize_nearest;
 
 /// Size 大小尺寸的定义
 ///
@@ -17,7 +18,7 @@ use crate::IiifError;
 /// let size: Size = "max".parse().unwrap();
 /// assert_eq!(size, Size::Max);
 /// ```
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Size {
     /// Format: `max`
     /// The extracted region is returned at the maximum size available, but will not be upscaled.
@@ -135,39 +136,69 @@ impl FromStr for Size {
 }
 
 impl Size {
+    /// 是否为以 `^` 开头的允许放大的尺寸
+    ///
+    /// Whether the size is prefixed with `^`, allowing the image to be upscaled.
+    pub fn is_upscaling(&self) -> bool {
+        matches!(
+            self,
+            Size::CMax
+                | Size::CW { .. }
+                | Size::CH { .. }
+                | Size::CPct { .. }
+                | Size::CWH { .. }
+                | Size::CLWH { .. }
+        )
+    }
+
     /// 处理图片缩放，返回缩放后的图片
     pub fn process(&self, image: DynamicImage) -> Result<DynamicImage, IiifError> {
-        let filter_type = FilterType::Nearest;
+        let (width, height) = self.get_size(image.width(), image.height())?;
+        if (width, height) == (image.width(), image.height()) {
+            return Ok(image);
+        }
+        Ok(resize_nearest(&image, width, height))
+    }
+
+    /// 根据提取区域的尺寸计算缩放后的尺寸，返回 (w, h)
+    ///
+    /// Calculates the scaled size for an extracted region of `width` x `height` pixels,
+    /// returns (w, h) without touching any pixels.
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::image::Size;
+    ///
+    /// let size = Size::LWH { w: 225, h: 100 };
+    /// assert_eq!(size.get_size(300, 200).unwrap(), (150, 100));
+    /// ```
+    pub fn get_size(&self, width: u32, height: u32) -> Result<(u32, u32), IiifError> {
         match self {
-            // TODO: maxWidth, maxHeight, maxArea
-            Self::Max => Ok(image),
-            // TODO: maxWidth, maxHeight, maxArea
-            Self::CMax => Ok(image),
+            // maxWidth、maxHeight、maxArea 由 `Size::resolve` 处理
+            Self::Max | Self::CMax => Ok((width, height)),
             Self::W { w } => {
-                if *w > image.width() {
+                if *w > width {
                     return Err(IiifError::BadRequest(
                         "Width is greater than image width".to_string(),
                     ));
                 }
-                Ok(image.resize(*w, image.height(), filter_type))
+                Ok(fit_dimensions(width, height, *w, height))
             }
             Self::CW { w } => {
-                let height =
-                    (image.height() as f32 * *w as f32 / image.width() as f32).round() as u32;
-                Ok(image.resize(*w, height, filter_type))
+                let h = (height as f32 * *w as f32 / width as f32).round() as u32;
+                Ok(fit_dimensions(width, height, *w, h))
             }
             Self::H { h } => {
-                if *h > image.height() {
+                if *h > height {
                     return Err(IiifError::BadRequest(
                         "Height is greater than image height".to_string(),
                     ));
                 }
-                Ok(image.resize(image.width(), *h, filter_type))
+                Ok(fit_dimensions(width, height, width, *h))
             }
             Self::CH { h } => {
-                let width =
-                    (image.width() as f32 * *h as f32 / image.height() as f32).round() as u32;
-                Ok(image.resize(width, *h, filter_type))
+                let w = (width as f32 * *h as f32 / height as f32).round() as u32;
+                Ok(fit_dimensions(width, height, w, *h))
             }
             Self::Pct { n } => {
                 if *n > 100.0 {
@@ -175,35 +206,37 @@ impl Size {
                         "Percentage is greater than 100".to_string(),
                     ));
                 }
-                Ok(image.resize(
-                    (image.width() as f32 * *n / 100.0).round() as u32,
-                    (image.height() as f32 * *n / 100.0).round() as u32,
-                    filter_type,
+                Ok(fit_dimensions(
+                    width,
+                    height,
+                    (width as f32 * *n / 100.0).round() as u32,
+                    (height as f32 * *n / 100.0).round() as u32,
                 ))
             }
-            Self::CPct { n } => Ok(image.resize(
-                (image.width() as f32 * *n / 100.0).round() as u32,
-                (image.height() as f32 * *n / 100.0).round() as u32,
-                filter_type,
+            Self::CPct { n } => Ok(fit_dimensions(
+                width,
+                height,
+                (width as f32 * *n / 100.0).round() as u32,
+                (height as f32 * *n / 100.0).round() as u32,
             )),
             Self::WH { w, h } => {
-                if *w > image.width() || *h > image.height() {
+                if *w > width || *h > height {
                     return Err(IiifError::BadRequest(
                         "Width or height is greater than image width or height".to_string(),
                     ));
                 }
-                Ok(image.resize_exact(*w, *h, filter_type))
+                Ok((*w, *h))
             }
-            Self::CWH { w, h } => Ok(image.resize_exact(*w, *h, filter_type)),
+            Self::CWH { w, h } => Ok((*w, *h)),
             Self::LWH { w, h } => {
-                if *w > image.width() || *h > image.height() {
+                if *w > width || *h > height {
                     return Err(IiifError::BadRequest(
                         "Width or height is greater than image width or height".to_string(),
                     ));
                 }
-                Ok(image.resize(*w, *h, filter_type))
+                Ok(fit_dimensions(width, height, *w, *h))
             }
-            Self::CLWH { w, h } => Ok(image.resize(*w, *h, filter_type)),
+            Self::CLWH { w, h } => Ok(fit_dimensions(width, height, *w, *h)),
         }
     }
 
@@ -231,7 +264,7 @@ impl Size {
         } else if n <= 100.0 {
             Some(Self::Pct { n })
         } else {
-            return None;
+            None
         }
     }
 
@@ -284,6 +317,19 @@ impl Size {
     }
 }
 
+/// 保持宽高比，计算能放入 `nwidth` x `nheight` 的最大尺寸（与 `DynamicImage::resize` 一致）
+fn fit_dimensions(width: u32, height: u32, nwidth: u32, nheight: u32) -> (u32, u32) {
+    let wratio = f64::from(nwidth) / f64::from(width);
+    let hratio = f64::from(nheight) / f64::from(height);
+    let ratio = f64::min(wratio, hratio);
+    let nw = ((f64::from(width) * ratio).round() as u64).max(1);
+    let nh = ((f64::from(height) * ratio).round() as u64).max(1);
+    (
+        nw.min(u64::from(u32::MAX)) as u32,
+        nh.min(u64::from(u32::MAX)) as u32,
+    )
+}
+
 impl Display for Size {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -412,6 +458,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_size_get_size() {
+        let cases = vec![
+            ("max", 300, 200),
+            ("150,", 150, 100),
+            ("^360,", 360, 240),
+            (",150", 225, 150),
+            ("pct:50", 150, 100),
+            ("225,100", 225, 100),
+            ("!225,100", 150, 100),
+            ("^!360,360", 360, 240),
+        ];
+        for case in cases {
+            let size = case.0.parse::<Size>().unwrap();
+            assert_eq!(size.get_size(300, 200).unwrap(), (case.1, case.2));
+        }
+        assert!(Size::W { w: 500 }.get_size(300, 200).is_err());
+    }
+
     #[test]
     fn test_size_process_error() {
         let storage = LocalStorage::new("./fixtures", "./fixtures/out");
@@ -0,0 +1,313 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+
+use crate::IiifError;
+use crate::image::page::{self, PdfImage};
+use crate::image::{ImagePlan, jpeg};
+
+/// 原图，加载时按 EXIF 方向信息转换为显示方向，并读取嵌入的 ICC 色彩配置文件
+///
+/// A source image. With `auto_orient`, the EXIF Orientation tag is applied while loading, so
+/// dimensions and region coordinates refer to the image as users see it. The embedded ICC
+/// profile is read along with the headers.
+///
+/// Multi-page sources are addressed with [`Source::page`]: a TIFF IFD, a GIF frame or the
+/// image embedded in a PDF page.
+///
+/// Example:
+/// ```
+/// use i3f::image::Source;
+///
+/// // rotated.jpg 的像素为 300x200，EXIF 方向为顺时针旋转 90 度
+/// let data = std::fs::read("./fixtures/rotated.jpg").unwrap();
+/// assert_eq!(Source::new(&data, true).unwrap().dimensions(), (200, 300));
+/// assert_eq!(Source::new(&data, false).unwrap().dimensions(), (300, 200));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Source<'a> {
+    data: Cow<'a, [u8]>,
+    frame: Frame,
+    width: u32,
+    height: u32,
+    orientation: Orientation,
+    icc_profile: Option<Vec<u8>>,
+}
+
+/// 需要解码的帧
+#[derive(Debug, Clone)]
+enum Frame {
+    /// 整个文件是单幅图像，或者多页文件的第一页
+    First,
+    /// TIFF 的第 n 个 IFD（从 0 开始）
+    Tiff(usize),
+    /// GIF 的第 n 帧（从 0 开始）
+    Gif(usize),
+    /// 已从 PDF 中解码的图像
+    Decoded(DynamicImage),
+}
+
+impl<'a> Source<'a> {
+    /// 读取图片头信息，不进行完整解码
+    ///
+    /// Reads the headers of `data` without decoding pixels. `auto_orient` selects whether the
+    /// EXIF orientation is applied or the raw sensor orientation is kept.
+    pub fn new(data: &'a [u8], auto_orient: bool) -> Result<Self, IiifError> {
+        Self::page(data, 1, auto_orient)
+    }
+
+    /// 读取多页原图中某一页的头信息，页码从 1 开始
+    ///
+    /// Reads the headers of the 1-based `page` of a multi-page source. PDF pages resolve to
+    /// the largest image embedded in the page, which is decoded eagerly unless it is a JPEG.
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::image::{Source, page_count};
+    ///
+    /// let data = std::fs::read("./fixtures/pages.tif").unwrap();
+    /// assert_eq!(page_count(&data).unwrap(), 3);
+    /// assert_eq!(Source::page(&data, 2, true).unwrap().dimensions(), (60, 40));
+    /// assert!(Source::page(&data, 4, true).is_err());
+    /// ```
+    pub fn page(data: &'a [u8], page: u32, auto_orient: bool) -> Result<Self, IiifError> {
+        if page == 0 {
+            return Err(IiifError::BadRequest("Pages start at 1".to_string()));
+        }
+        if page::is_pdf(data) {
+            return match page::pdf_image(data, page)? {
+                PdfImage::Jpeg(jpeg, icc_profile) => {
+                    let mut source = Self::first(Cow::Owned(jpeg), auto_orient)?;
+                    source.icc_profile = source.icc_profile.or(icc_profile);
+                    Ok(source)
+                }
+                PdfImage::Decoded(image, icc_profile) => Ok(Self {
+                    data: Cow::Borrowed(data),
+                    width: image.width(),
+                    height: image.height(),
+                    frame: Frame::Decoded(image),
+                    orientation: Orientation::NoTransforms,
+                    icc_profile,
+                }),
+            };
+        }
+        let is_tiff = matches!(image::guess_format(data), Ok(ImageFormat::Tiff));
+        if page == 1 {
+            // image 不支持的 TIFF（如 JPEG 压缩的 YCbCr）改为按 IFD 解码
+            return Self::first(Cow::Borrowed(data), auto_orient).or_else(|e| {
+                if is_tiff {
+                    Self::tiff(data, 0, auto_orient)
+                } else {
+                    Err(e)
+                }
+            });
+        }
+
+        let index = page as usize - 1;
+        match image::guess_format(data) {
+            Ok(ImageFormat::Tiff) => Self::tiff(data, index, auto_orient),
+            Ok(ImageFormat::Gif) => {
+                // GIF 的每一帧都合成到整个画布上，尺寸与第一帧相同。
+                // 统计帧数需要解码全部帧，帧不存在时在解码阶段返回 NotFound
+                let first = Self::first(Cow::Borrowed(data), auto_orient)?;
+                Ok(Self {
+                    frame: Frame::Gif(index),
+                    ..first
+                })
+            }
+            _ => Err(IiifError::NotFound(format!("Page {page} not found"))),
+        }
+    }
+
+    /// 读取 TIFF 第 `index` 个 IFD 的头信息
+    fn tiff(data: &'a [u8], index: usize, auto_orient: bool) -> Result<Self, IiifError> {
+        let tiff = page::tiff_page(data, index)?;
+        Ok(Self {
+            data: Cow::Borrowed(data),
+            frame: Frame::Tiff(index),
+            width: tiff.width,
+            height: tiff.height,
+            orientation: if auto_orient {
+                tiff.orientation
+            } else {
+                Orientation::NoTransforms
+            },
+            icc_profile: tiff.icc_profile,
+        })
+    }
+
+    /// 读取单幅图像或多页文件第一页的头信息
+    fn first(data: Cow<'a, [u8]>, auto_orient: bool) -> Result<Self, IiifError> {
+        let mut decoder = reader(&data)?.into_decoder().map_err(internal_error)?;
+        let (width, height) = decoder.dimensions();
+        let orientation = if auto_orient {
+            decoder.orientation().map_err(internal_error)?
+        } else {
+            Orientation::NoTransforms
+        };
+        let icc_profile = decoder.icc_profile().map_err(internal_error)?;
+        // 解码器借用了 data，移动 data 前先释放
+        drop(decoder);
+        Ok(Self {
+            data,
+            frame: Frame::First,
+            width,
+            height,
+            orientation,
+            icc_profile,
+        })
+    }
+
+    /// 应用的方向转换
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// 嵌入的 ICC 色彩配置文件
+    ///
+    /// The embedded ICC profile, if any.
+    pub fn icc_profile(&self) -> Option<&[u8]> {
+        self.icc_profile.as_deref()
+    }
+
+    /// 显示方向下的尺寸
+    ///
+    /// The dimensions after the orientation is applied.
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self.orientation {
+            Orientation::Rotate90
+            | Orientation::Rotate270
+            | Orientation::Rotate90FlipH
+            | Orientation::Rotate270FlipH => (self.height, self.width),
+            _ => (self.width, self.height),
+        }
+    }
+
+    /// 按处理计划解码：目标远小于区域时，8 位 JPEG 利用 DCT 缩放直接解码为 1/2、1/4 或
+    /// 1/8 尺寸，并返回与解码结果对应的处理计划
+    ///
+    /// Decodes the image for `plan`. When the scaled size is at most half of the crop, an
+    /// 8-bit gray or colour JPEG is decoded at 1/2, 1/4 or 1/8 of its size with the DCT
+    /// scaling of `jpeg-decoder`, and the returned plan is reduced to match, see
+    /// [`ImagePlan::downscaled`]. The remaining resize still produces exactly the planned
+    /// dimensions. Other sources are decoded in full with `plan` unchanged.
+    pub fn decode_for(&self, plan: &ImagePlan) -> Result<(DynamicImage, ImagePlan), IiifError> {
+        let denominator = plan.decode_scale();
+        if denominator > 1
+            && matches!(self.frame, Frame::First)
+            && let Some(mut image) = jpeg::decode_scaled(&self.data, denominator)
+            && (image.width(), image.height())
+                == (
+                    self.width.div_ceil(denominator),
+                    self.height.div_ceil(denominator),
+                )
+        {
+            image.apply_orientation(self.orientation);
+            return Ok((image, plan.downscaled(denominator)));
+        }
+        Ok((self.decode()?, *plan))
+    }
+
+    /// 解码并转换为显示方向
+    ///
+    /// Decodes the image and applies the orientation.
+    pub fn decode(&self) -> Result<DynamicImage, IiifError> {
+        let mut image = match &self.frame {
+            Frame::First => reader(&self.data)?.decode().map_err(internal_error)?,
+            Frame::Tiff(index) => page::decode_tiff_page(&self.data, *index)?,
+            Frame::Gif(index) => page::decode_gif_frame(&self.data, *index)?,
+            Frame::Decoded(image) => image.clone(),
+        };
+        image.apply_orientation(self.orientation);
+        Ok(image)
+    }
+}
+
+fn reader(data: &[u8]) -> Result<ImageReader<Cursor<&[u8]>>, IiifError> {
+    ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(internal_error)
+}
+
+fn internal_error(e: impl std::fmt::Display) -> IiifError {
+    IiifError::InternalServerError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::GenericImageView;
+    use image::imageops::FilterType;
+
+    use super::*;
+    use crate::image::{IiifImage, Limits, Rect};
+
+    #[test]
+    fn test_source_orientation() {
+        let data = std::fs::read("./fixtures/rotated.jpg").unwrap();
+        let raw = Source::new(&data, false).unwrap().decode().unwrap();
+        let source = Source::new(&data, true).unwrap();
+        assert_eq!(source.orientation(), Orientation::Rotate90);
+        let image = source.decode().unwrap();
+        assert_eq!(image.dimensions(), source.dimensions());
+        assert_eq!(image.to_rgb8(), raw.rotate90().to_rgb8());
+
+        // 没有 EXIF 方向信息的图片保持不变
+        let data = std::fs::read("./fixtures/demo.jpg").unwrap();
+        let source = Source::new(&data, true).unwrap();
+        assert_eq!(source.orientation(), Orientation::NoTransforms);
+        assert_eq!(source.dimensions(), (300, 200));
+    }
+
+    #[test]
+    fn test_source_decode_for() {
+        let url = "https://example.org/iiif/rotated.jpg/0,100,200,200/!25,25/0/default.jpg";
+        let request = IiifImage::try_from(url::Url::parse(url).unwrap()).unwrap();
+        let data = std::fs::read("./fixtures/rotated.jpg").unwrap();
+        let source = Source::new(&data, true).unwrap();
+        let plan = request.plan(200, 300, &Limits::default()).unwrap();
+        let (image, reduced) = source.decode_for(&plan).unwrap();
+        assert_eq!(plan.decode_scale(), 8);
+        assert_eq!(image.dimensions(), (25, 38));
+        assert_eq!((reduced.source_width, reduced.source_height), (25, 38));
+        assert_eq!(reduced.crop, Rect::new(0, 12, 25, 26));
+
+        // 缩小解码已转换为显示方向，与完整解码后缩小的结果相近
+        let crop = reduced.crop;
+        let output = image
+            .crop_imm(crop.x, crop.y, crop.width, crop.height)
+            .resize_exact(25, 25, FilterType::Nearest)
+            .to_rgb8();
+        let crop = plan.crop;
+        let expected = source
+            .decode()
+            .unwrap()
+            .crop_imm(crop.x, crop.y, crop.width, crop.height)
+            .resize_exact(25, 25, FilterType::Triangle)
+            .to_rgb8();
+        let sum = expected
+            .iter()
+            .zip(output.iter())
+            .map(|(&a, &b)| a.abs_diff(b) as u64);
+        let error = sum.sum::<u64>() as f64 / expected.len() as f64;
+        assert!(error < 16.0, "{error}");
+
+        // 非 JPEG 原图完整解码，计划不变
+        let request =
+            IiifImage::try_from(url::Url::parse(&url.replace("0,100,200,200", "full")).unwrap())
+                .unwrap();
+        let data = std::fs::read("./fixtures/pages.tif").unwrap();
+        let source = Source::new(&data, true).unwrap();
+        let (width, height) = source.dimensions();
+        let plan = request.plan(width, height, &Limits::default()).unwrap();
+        let (image, unchanged) = source.decode_for(&plan).unwrap();
+        assert_eq!(unchanged, plan);
+        assert_eq!(image.dimensions(), (width, height));
+    }
+
+    #[test]
+    fn test_source_error() {
+        assert!(Source::new(b"not an image", true).is_err());
+    }
+}
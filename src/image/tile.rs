@@ -0,0 +1,351 @@
+use crate::image::{Format, IiifImage, ImageInfo, Quality, Rect, Region, Rotation, Size, TileInfo};
+
+/// 瓦片网格，根据原图尺寸和 `TileInfo` 计算每个缩放因子下的瓦片
+///
+/// The tile grid of an image, computed from its dimensions and a `TileInfo` following the
+/// tile math of the [specification](https://iiif.io/api/image/3.0/implementation/#3-tiled-images).
+///
+/// Example:
+/// ```
+/// use i3f::image::{ImageInfo, Rect, TileGrid, TileInfo};
+///
+/// let info = ImageInfo {
+///     id: "https://example.org/iiif/demo.jpg".to_string(),
+///     width: 300,
+///     height: 200,
+///     tiles: Some(vec![TileInfo { r#type: None, scale_factors: vec![1, 2], width: 128, height: None }]),
+///     ..Default::default()
+/// };
+/// let grid = &TileGrid::from_info(&info)[0];
+/// assert_eq!((grid.columns(1), grid.rows(1)), (3, 2));
+///
+/// let tile = grid.tile(1, 2, 1).unwrap();
+/// assert_eq!(tile.region, Rect::new(256, 128, 44, 72));
+/// assert_eq!(
+///     grid.tile_url(&tile, &info.id),
+///     "https://example.org/iiif/demo.jpg/256,128,44,72/max/0/default.jpg"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileGrid {
+    /// 原图尺寸
+    pub width: u32,
+    pub height: u32,
+
+    /// 瓦片尺寸（缩放后）
+    pub tile_width: u32,
+    pub tile_height: u32,
+
+    /// 缩放因子，从小到大
+    pub scale_factors: Vec<u32>,
+}
+
+/// 单个瓦片
+///
+/// A single tile of a `TileGrid`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    /// 缩放因子
+    pub scale_factor: u32,
+
+    /// 列号和行号，从 0 开始
+    pub column: u32,
+    pub row: u32,
+
+    /// 瓦片在原图中的区域
+    ///
+    /// The region of the tile in full image coordinates.
+    pub region: Rect,
+
+    /// 瓦片的像素尺寸
+    ///
+    /// The pixel dimensions of the returned tile.
+    pub width: u32,
+    pub height: u32,
+}
+
+impl TileGrid {
+    /// 根据原图尺寸和 `TileInfo` 创建瓦片网格
+    pub fn new(width: u32, height: u32, tile: &TileInfo) -> Self {
+        let mut scale_factors = tile
+            .scale_factors
+            .iter()
+            .map(|&factor| u32::from(factor).max(1))
+            .collect::<Vec<_>>();
+        scale_factors.sort_unstable();
+        scale_factors.dedup();
+        Self {
+            width,
+            height,
+            tile_width: tile.width,
+            tile_height: tile.height.unwrap_or(tile.width),
+            scale_factors,
+        }
+    }
+
+    /// 根据图像信息中的每个 `TileInfo` 创建瓦片网格
+    pub fn from_info(info: &ImageInfo) -> Vec<Self> {
+        info.tiles
+            .iter()
+            .flatten()
+            .map(|tile| Self::new(info.width, info.height, tile))
+            .collect()
+    }
+
+    /// 生成瓦片对应的请求，参数使用规范形式
+    ///
+    /// Returns the request for `tile`, in canonical form so that it shares the derivative
+    /// cache with equivalent requests.
+    pub fn tile_image(&self, tile: &Tile, identifier: &str) -> IiifImage {
+        let region = tile.region;
+        IiifImage {
+            identifier: identifier.to_string(),
+            region: if region == Rect::new(0, 0, self.width, self.height) {
+                Region::Full
+            } else {
+                Region::Rect(region.x, region.y, region.width, region.height)
+            },
+            size: if (tile.width, tile.height) == (region.width, region.height) {
+                Size::Max
+            } else {
+                Size::WH {
+                    w: tile.width,
+                    h: tile.height,
+                }
+            },
+            rotation: Rotation::Degrees(0.0),
+            quality: Quality::Default,
+            format: Format::Jpg,
+        }
+    }
+
+    /// 生成瓦片的完整 URL，`base_uri` 为图像的基础 URI
+    ///
+    /// Returns the URL of `tile`, `base_uri` being the base URI of the image (the `id` of its
+    /// image information).
+    pub fn tile_url(&self, tile: &Tile, base_uri: &str) -> String {
+        let image = self.tile_image(tile, "");
+        format!(
+            "{}/{}/{}/{}/{}.{}",
+            base_uri.trim_end_matches('/'),
+            image.region,
+            image.size,
+            image.rotation,
+            image.quality,
+            image.format
+        )
+    }
+
+    /// 某个缩放因子下的列数
+    pub fn columns(&self, scale_factor: u32) -> u32 {
+        self.width.div_ceil(span(self.tile_width, scale_factor))
+    }
+
+    /// 某个缩放因子下的行数
+    pub fn rows(&self, scale_factor: u32) -> u32 {
+        self.height.div_ceil(span(self.tile_height, scale_factor))
+    }
+
+    /// 获取指定位置的瓦片，越界时返回 `None`
+    ///
+    /// Returns the tile at `column` x `row` for `scale_factor`, or `None` if out of the grid.
+    pub fn tile(&self, scale_factor: u32, column: u32, row: u32) -> Option<Tile> {
+        if scale_factor == 0
+            || column >= self.columns(scale_factor)
+            || row >= self.rows(scale_factor)
+        {
+            return None;
+        }
+        let span_x = span(self.tile_width, scale_factor);
+        let span_y = span(self.tile_height, scale_factor);
+        let x = column * span_x;
+        let y = row * span_y;
+        let w = span_x.min(self.width - x);
+        let h = span_y.min(self.height - y);
+        Some(Tile {
+            scale_factor,
+            column,
+            row,
+            region: Rect::new(x, y, w, h),
+            width: w.div_ceil(scale_factor),
+            height: h.div_ceil(scale_factor),
+        })
+    }
+
+    /// 某个缩放因子下的全部瓦片，按行优先排列
+    pub fn tiles(&self, scale_factor: u32) -> impl Iterator<Item = Tile> + '_ {
+        self.tiles_in(Rect::new(0, 0, self.width, self.height), scale_factor)
+    }
+
+    /// 所有缩放因子下的全部瓦片
+    pub fn all_tiles(&self) -> impl Iterator<Item = Tile> + '_ {
+        self.scale_factors
+            .iter()
+            .flat_map(|&scale_factor| self.tiles(scale_factor))
+    }
+
+    /// 覆盖视口的瓦片，视口使用原图坐标
+    ///
+    /// Returns the tiles of `scale_factor` covering `viewport`, given in full image coordinates.
+    pub fn tiles_in(&self, viewport: Rect, scale_factor: u32) -> impl Iterator<Item = Tile> + '_ {
+        let (columns, rows) = if scale_factor == 0
+            || viewport.width == 0
+            || viewport.height == 0
+            || viewport.x >= self.width
+            || viewport.y >= self.height
+        {
+            (0..0, 0..0)
+        } else {
+            let span_x = span(self.tile_width, scale_factor);
+            let span_y = span(self.tile_height, scale_factor);
+            let right = viewport.x.saturating_add(viewport.width).min(self.width);
+            let bottom = viewport.y.saturating_add(viewport.height).min(self.height);
+            (
+                viewport.x / span_x..right.div_ceil(span_x),
+                viewport.y / span_y..bottom.div_ceil(span_y),
+            )
+        };
+        rows.flat_map(move |row| {
+            columns
+                .clone()
+                .filter_map(move |column| self.tile(scale_factor, column, row))
+        })
+    }
+
+    /// 选择适合显示比例的缩放因子，即不大于 `1 / scale` 的最大缩放因子
+    ///
+    /// Chooses the scale factor for displaying the image at `scale` (e.g. `0.25` for a
+    /// quarter of the full size): the largest factor that does not lose resolution.
+    pub fn scale_factor_for(&self, scale: f64) -> u32 {
+        let target = 1.0 / scale;
+        self.scale_factors
+            .iter()
+            .rev()
+            .find(|&&factor| f64::from(factor) <= target)
+            .or(self.scale_factors.first())
+            .copied()
+            .unwrap_or(1)
+    }
+}
+
+/// 瓦片在原图中的跨度，瓦片尺寸为 0 或乘积溢出时视为覆盖整幅图像的单个瓦片
+///
+/// The extent of a tile in full image coordinates. A zero tile size or a product overflowing
+/// `u32` yields a single tile spanning the whole image.
+fn span(tile_size: u32, scale_factor: u32) -> u32 {
+    tile_size
+        .checked_mul(scale_factor)
+        .filter(|&span| span > 0)
+        .unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid() -> TileGrid {
+        TileGrid::new(
+            1000,
+            700,
+            &TileInfo {
+                r#type: None,
+                scale_factors: vec![4, 1, 2],
+                width: 256,
+                height: None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_tile_grid() {
+        let grid = grid();
+        assert_eq!(grid.scale_factors, vec![1, 2, 4]);
+        assert_eq!((grid.columns(1), grid.rows(1)), (4, 3));
+        assert_eq!((grid.columns(2), grid.rows(2)), (2, 2));
+        assert_eq!((grid.columns(4), grid.rows(4)), (1, 1));
+        assert_eq!(grid.all_tiles().count(), 12 + 4 + 1);
+
+        let tile = grid.tile(2, 1, 1).unwrap();
+        assert_eq!(tile.region, Rect::new(512, 512, 488, 188));
+        assert_eq!((tile.width, tile.height), (244, 94));
+        assert!(grid.tile(2, 2, 0).is_none());
+
+        // 每个缩放因子下瓦片恰好覆盖整幅图像
+        for &factor in &grid.scale_factors {
+            let area: u64 = grid
+                .tiles(factor)
+                .map(|tile| u64::from(tile.region.width) * u64::from(tile.region.height))
+                .sum();
+            assert_eq!(area, 1000 * 700);
+        }
+    }
+
+    #[test]
+    fn test_tile_grid_degenerate() {
+        let tile = |width, scale_factors: Vec<u8>| TileInfo {
+            r#type: None,
+            scale_factors,
+            width,
+            height: None,
+        };
+
+        // 瓦片尺寸为 0 时整幅图像为单个瓦片
+        let grid = TileGrid::new(1000, 700, &tile(0, vec![1, 2]));
+        assert_eq!((grid.columns(1), grid.rows(1)), (1, 1));
+        let tile_1 = grid.tile(1, 0, 0).unwrap();
+        assert_eq!(tile_1.region, Rect::new(0, 0, 1000, 700));
+        assert_eq!((tile_1.width, tile_1.height), (1000, 700));
+        assert_eq!(grid.tile(2, 0, 0).unwrap().width, 500);
+        assert_eq!(grid.all_tiles().count(), 2);
+
+        // 乘积溢出时同样为单个瓦片
+        let grid = TileGrid::new(1000, 700, &tile(1 << 31, vec![1, 2]));
+        assert_eq!((grid.columns(2), grid.rows(2)), (1, 1));
+        let tile = grid.tile(2, 0, 0).unwrap();
+        assert_eq!(tile.region, Rect::new(0, 0, 1000, 700));
+        assert_eq!((tile.width, tile.height), (500, 350));
+        let viewport = Rect::new(0, 0, 1000, 700);
+        assert_eq!(grid.tiles_in(viewport, 2).count(), 1);
+    }
+
+    #[test]
+    fn test_tile_urls() {
+        let grid = grid();
+        let base = "https://example.org/iiif/demo.jpg/";
+        assert_eq!(
+            grid.tile_url(&grid.tile(4, 0, 0).unwrap(), base),
+            "https://example.org/iiif/demo.jpg/full/250,175/0/default.jpg"
+        );
+        assert_eq!(
+            grid.tile_url(&grid.tile(2, 1, 0).unwrap(), base),
+            "https://example.org/iiif/demo.jpg/512,0,488,512/244,256/0/default.jpg"
+        );
+        assert_eq!(
+            grid.tile_url(&grid.tile(1, 3, 2).unwrap(), base),
+            "https://example.org/iiif/demo.jpg/768,512,232,188/max/0/default.jpg"
+        );
+
+        // 瓦片请求与规范形式一致
+        for tile in grid.all_tiles() {
+            let image = grid.tile_image(&tile, "demo.jpg");
+            assert_eq!(image.canonical(grid.width, grid.height).unwrap(), image);
+        }
+    }
+
+    #[test]
+    fn test_tiles_in_viewport() {
+        let grid = grid();
+        let tiles = grid
+            .tiles_in(Rect::new(200, 300, 100, 300), 1)
+            .map(|tile| (tile.column, tile.row))
+            .collect::<Vec<_>>();
+        assert_eq!(tiles, vec![(0, 1), (1, 1), (0, 2), (1, 2)]);
+        assert_eq!(grid.tiles_in(Rect::new(0, 0, 5000, 5000), 2).count(), 4);
+        assert_eq!(grid.tiles_in(Rect::new(1000, 0, 10, 10), 1).count(), 0);
+
+        assert_eq!(grid.scale_factor_for(1.0), 1);
+        assert_eq!(grid.scale_factor_for(0.3), 2);
+        assert_eq!(grid.scale_factor_for(0.1), 4);
+        assert_eq!(grid.scale_factor_for(2.0), 1);
+    }
+}
@@ -0,0 +1,348 @@
+use std::io::{Cursor, Seek, Write};
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ExtendedColorType, ImageEncoder};
+use tiff::encoder::TiffEncoder;
+use tiff::encoder::compression::{CompressionAlgorithm, Deflate, Lzw};
+use tiff::tags::{CompressionMethod, PhotometricInterpretation, PlanarConfiguration, Tag};
+
+use crate::IiifError;
+use crate::image::{is_high_bit_depth, keeps_gray};
+
+/// 分块 TIFF 的瓦片压缩方式
+///
+/// The compression of the tiles of a tiled TIFF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TiffCompression {
+    /// 无损 Deflate（Adobe），保留 16 位和透明通道
+    ///
+    /// Lossless Adobe Deflate, keeps 16-bit samples and alpha.
+    #[default]
+    Deflate,
+
+    /// 无损 LZW，保留 16 位和透明通道
+    ///
+    /// Lossless LZW, keeps 16-bit samples and alpha.
+    Lzw,
+
+    /// 指定质量（1-100）的 JPEG，彩色瓦片以 YCbCr 存储，不支持透明通道和 16 位
+    ///
+    /// JPEG with the given quality (1-100). Color tiles are stored as YCbCr without chroma
+    /// subsampling, alpha is dropped and samples are reduced to 8 bits.
+    Jpeg(u8),
+}
+
+/// 分块金字塔 TIFF 输出
+///
+/// Writes tiled, multi-resolution (pyramidal) TIFFs: the full resolution image in the first
+/// IFD, followed by reduced-resolution IFDs (`NewSubfileType` 1) halving the size until the
+/// image fits in a single tile. This is the layout read by common IIIF servers, and each
+/// level can be addressed as a page with this library (`image.tif;page=2`).
+///
+/// Example:
+/// ```
+/// use i3f::image::{Source, TiffCompression, TiledTiff};
+///
+/// let image = image::open("./fixtures/demo.jpg").unwrap();
+/// let tiff = TiledTiff { tile_size: 256, compression: TiffCompression::Lzw };
+/// let data = tiff.encode(&image, None).unwrap();
+///
+/// // 300x200 -> 150x100
+/// assert_eq!(i3f::image::page_count(&data).unwrap(), 2);
+/// assert_eq!(Source::page(&data, 2, true).unwrap().dimensions(), (150, 100));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TiledTiff {
+    /// 瓦片边长，必须是 16 的倍数
+    ///
+    /// The width and height of the tiles, a multiple of 16 as required by TIFF.
+    pub tile_size: u32,
+
+    /// 瓦片压缩方式
+    ///
+    /// The compression of the tiles.
+    pub compression: TiffCompression,
+}
+
+impl Default for TiledTiff {
+    fn default() -> Self {
+        Self {
+            tile_size: 256,
+            compression: TiffCompression::default(),
+        }
+    }
+}
+
+impl TiledTiff {
+    /// 编码为分块金字塔 TIFF，配置文件嵌入每一层
+    ///
+    /// Encodes the image as a pyramidal TIFF with the ICC profile embedded in every level.
+    pub fn encode(
+        &self,
+        image: &DynamicImage,
+        icc_profile: Option<&[u8]>,
+    ) -> Result<Vec<u8>, IiifError> {
+        if self.tile_size == 0 || !self.tile_size.is_multiple_of(16) {
+            return Err(IiifError::InternalServerError(format!(
+                "TIFF tile size must be a positive multiple of 16, got {}",
+                self.tile_size
+            )));
+        }
+        let mut bytes = Vec::new();
+        let mut encoder = TiffEncoder::new(Cursor::new(&mut bytes)).map_err(tiff_error)?;
+        let mut level = self.layout(image, icc_profile);
+        let mut reduced = false;
+        loop {
+            self.write_level(&mut encoder, &level, reduced, icc_profile)?;
+            let (width, height) = (level.width(), level.height());
+            if width <= self.tile_size && height <= self.tile_size {
+                break;
+            }
+            level = level.resize_exact(width.div_ceil(2), height.div_ceil(2), FilterType::Triangle);
+            reduced = true;
+        }
+        Ok(bytes)
+    }
+
+    /// 瓦片的像素布局：JPEG 为 8 位灰度或 RGB，无损压缩保留 16 位和透明通道。
+    /// 嵌入配置文件时按其色彩空间决定是否为灰度，否则灰度图像保持灰度
+    fn layout(&self, image: &DynamicImage, icc_profile: Option<&[u8]>) -> DynamicImage {
+        let gray = keeps_gray(image, icc_profile);
+        let alpha = image.color().has_alpha();
+        match (self.compression, is_high_bit_depth(image), gray, alpha) {
+            (TiffCompression::Jpeg(_), _, true, _) => DynamicImage::ImageLuma8(image.to_luma8()),
+            (TiffCompression::Jpeg(_), ..) => DynamicImage::ImageRgb8(image.to_rgb8()),
+            (_, true, true, true) => DynamicImage::ImageLumaA16(image.to_luma_alpha16()),
+            (_, true, false, true) => DynamicImage::ImageRgba16(image.to_rgba16()),
+            (_, true, true, false) => DynamicImage::ImageLuma16(image.to_luma16()),
+            (_, true, false, false) => DynamicImage::ImageRgb16(image.to_rgb16()),
+            (_, false, true, true) => DynamicImage::ImageLumaA8(image.to_luma_alpha8()),
+            (_, false, false, true) => DynamicImage::ImageRgba8(image.to_rgba8()),
+            (_, false, true, false) => DynamicImage::ImageLuma8(image.to_luma8()),
+            (_, false, false, false) => DynamicImage::ImageRgb8(image.to_rgb8()),
+        }
+    }
+
+    /// 写入一层：按行优先顺序写入全部瓦片，边缘瓦片以 0 填充到完整大小
+    fn write_level<W: Write + Seek>(
+        &self,
+        encoder: &mut TiffEncoder<W>,
+        image: &DynamicImage,
+        reduced: bool,
+        icc_profile: Option<&[u8]>,
+    ) -> Result<(), IiifError> {
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let color = image.color();
+        let channels = color.channel_count() as usize;
+        let pixel_bytes = color.bytes_per_pixel() as usize;
+        let tile = self.tile_size as usize;
+        let samples = image.as_bytes();
+
+        let mut directory = encoder.image_directory().map_err(tiff_error)?;
+        let mut offsets = Vec::new();
+        let mut byte_counts = Vec::new();
+        for tile_y in (0..height).step_by(tile) {
+            for tile_x in (0..width).step_by(tile) {
+                let mut buf = vec![0u8; tile * tile * pixel_bytes];
+                let row_bytes = tile.min(width - tile_x) * pixel_bytes;
+                for row in 0..tile.min(height - tile_y) {
+                    let start = ((tile_y + row) * width + tile_x) * pixel_bytes;
+                    buf[row * tile * pixel_bytes..][..row_bytes]
+                        .copy_from_slice(&samples[start..start + row_bytes]);
+                }
+                let data = self.compress(&buf, channels)?;
+                let offset = directory.write_data(data.as_slice()).map_err(tiff_error)?;
+                offsets.push(u32::try_from(offset).map_err(|_| {
+                    IiifError::InternalServerError("TIFF output exceeds 4 GiB".to_string())
+                })?);
+                byte_counts.push(data.len() as u32);
+            }
+        }
+
+        let bits = (8 * pixel_bytes / channels) as u16;
+        let compression = match self.compression {
+            TiffCompression::Deflate => CompressionMethod::Deflate,
+            TiffCompression::Lzw => CompressionMethod::LZW,
+            TiffCompression::Jpeg(_) => CompressionMethod::ModernJPEG,
+        };
+        let photometric = match (self.compression, color.has_color()) {
+            (_, false) => PhotometricInterpretation::BlackIsZero,
+            (TiffCompression::Jpeg(_), _) => PhotometricInterpretation::YCbCr,
+            _ => PhotometricInterpretation::RGB,
+        };
+        let result = (|| {
+            directory.write_tag(Tag::NewSubfileType, if reduced { 1u32 } else { 0u32 })?;
+            directory.write_tag(Tag::ImageWidth, width as u32)?;
+            directory.write_tag(Tag::ImageLength, height as u32)?;
+            directory.write_tag(Tag::BitsPerSample, vec![bits; channels].as_slice())?;
+            directory.write_tag(Tag::Compression, compression)?;
+            directory.write_tag(Tag::PhotometricInterpretation, photometric)?;
+            directory.write_tag(Tag::SamplesPerPixel, channels as u16)?;
+            directory.write_tag(Tag::PlanarConfiguration, PlanarConfiguration::Chunky)?;
+            directory.write_tag(Tag::TileWidth, self.tile_size)?;
+            directory.write_tag(Tag::TileLength, self.tile_size)?;
+            directory.write_tag(Tag::TileOffsets, offsets.as_slice())?;
+            directory.write_tag(Tag::TileByteCounts, byte_counts.as_slice())?;
+            if color.has_alpha() {
+                // 非预乘透明通道
+                directory.write_tag(Tag::ExtraSamples, 2u16)?;
+            }
+            if photometric == PhotometricInterpretation::YCbCr {
+                // JPEG 编码器不对色度降采样
+                directory.write_tag(Tag::ChromaSubsampling, [1u16, 1].as_slice())?;
+            }
+            if let Some(icc_profile) = icc_profile {
+                directory.write_tag(Tag::IccProfile, icc_profile)?;
+            }
+            directory.finish()
+        })();
+        result.map_err(tiff_error)
+    }
+
+    /// 压缩一个瓦片
+    fn compress(&self, buf: &[u8], channels: usize) -> Result<Vec<u8>, IiifError> {
+        let mut data = Vec::new();
+        let tile = self.tile_size;
+        match self.compression {
+            TiffCompression::Deflate => {
+                Deflate::default()
+                    .write_to(&mut data, buf)
+                    .map_err(tiff_error)?;
+            }
+            TiffCompression::Lzw => {
+                Lzw.write_to(&mut data, buf).map_err(tiff_error)?;
+            }
+            TiffCompression::Jpeg(quality) => {
+                let color_type = if channels == 1 {
+                    ExtendedColorType::L8
+                } else {
+                    ExtendedColorType::Rgb8
+                };
+                JpegEncoder::new_with_quality(&mut data, quality.clamp(1, 100))
+                    .write_image(buf, tile, tile, color_type)
+                    .map_err(|e| {
+                        IiifError::InternalServerError(format!("Failed to encode JPEG tile: {e}"))
+                    })?;
+            }
+        }
+        Ok(data)
+    }
+}
+
+fn tiff_error(e: impl std::fmt::Display) -> IiifError {
+    IiifError::InternalServerError(format!("Failed to encode TIF image: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+    use tiff::decoder::Decoder;
+
+    use super::*;
+    use crate::image::{Format, ServiceConfig, Source};
+
+    fn tag(decoder: &mut Decoder<Cursor<&[u8]>>, tag: Tag) -> Vec<u32> {
+        decoder.get_tag_u32_vec(tag).unwrap()
+    }
+
+    #[test]
+    fn test_tiled_tiff_levels() {
+        let image = image::open("./fixtures/demo.jpg").unwrap();
+        let config = ServiceConfig {
+            tiff: Some(TiledTiff {
+                tile_size: 128,
+                compression: TiffCompression::Deflate,
+            }),
+            ..Default::default()
+        };
+        let data = Format::Tif
+            .encode_with(image.clone(), None, &config)
+            .unwrap();
+
+        let mut decoder = Decoder::new(Cursor::new(data.as_slice())).unwrap();
+        assert_eq!(decoder.dimensions().unwrap(), (300, 200));
+        assert_eq!(tag(&mut decoder, Tag::TileWidth), vec![128]);
+        assert_eq!(tag(&mut decoder, Tag::TileOffsets).len(), 6);
+        assert_eq!(tag(&mut decoder, Tag::Compression), vec![8]);
+        assert_eq!(tag(&mut decoder, Tag::NewSubfileType), vec![0]);
+        decoder.next_image().unwrap();
+        assert_eq!(decoder.dimensions().unwrap(), (150, 100));
+        assert_eq!(tag(&mut decoder, Tag::TileOffsets).len(), 2);
+        assert_eq!(tag(&mut decoder, Tag::NewSubfileType), vec![1]);
+        decoder.next_image().unwrap();
+        assert_eq!(decoder.dimensions().unwrap(), (75, 50));
+        assert!(!decoder.more_images());
+
+        // 无损压缩的第一层与原图一致
+        let source = Source::new(&data, true).unwrap();
+        assert_eq!(source.decode().unwrap().to_rgb8(), image.to_rgb8());
+    }
+
+    #[test]
+    fn test_tiled_tiff_16bit_and_alpha() {
+        let data = std::fs::read("./fixtures/deep.tif").unwrap();
+        let image = Source::new(&data, true).unwrap().decode().unwrap();
+        let tiled = TiledTiff {
+            tile_size: 16,
+            compression: TiffCompression::Lzw,
+        };
+        let output = tiled.encode(&image, None).unwrap();
+        assert_eq!(crate::image::page_count(&output).unwrap(), 3);
+        let decoded = Source::new(&output, true).unwrap().decode().unwrap();
+        assert!(matches!(decoded, DynamicImage::ImageRgb16(_)));
+        assert_eq!(decoded, image);
+        let smallest = Source::page(&output, 3, true).unwrap();
+        assert_eq!(smallest.dimensions(), (16, 12));
+
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(40, 20, Rgba([1, 2, 3, 4])));
+        let output = TiledTiff::default().encode(&image, None).unwrap();
+        assert_eq!(Source::new(&output, true).unwrap().decode().unwrap(), image);
+    }
+
+    #[test]
+    fn test_tiled_tiff_jpeg() {
+        let image = image::open("./fixtures/demo.jpg").unwrap();
+        let tiled = TiledTiff {
+            tile_size: 64,
+            compression: TiffCompression::Jpeg(90),
+        };
+        let data = tiled.encode(&image, None).unwrap();
+        let mut decoder = Decoder::new(Cursor::new(data.as_slice())).unwrap();
+        assert_eq!(tag(&mut decoder, Tag::Compression), vec![7]);
+        assert_eq!(tag(&mut decoder, Tag::PhotometricInterpretation), vec![6]);
+        assert_eq!(tag(&mut decoder, Tag::TileOffsets).len(), 5 * 4);
+
+        // JPEG 有损，解码结果与原图接近
+        let decoded = Source::new(&data, true)
+            .unwrap()
+            .decode()
+            .unwrap()
+            .to_rgb8();
+        assert_eq!(decoded.dimensions(), (300, 200));
+        let diff = decoded
+            .as_raw()
+            .iter()
+            .zip(image.to_rgb8().as_raw())
+            .map(|(a, b)| a.abs_diff(*b) as u64)
+            .sum::<u64>()
+            / decoded.as_raw().len() as u64;
+        assert!(diff < 4, "mean difference {diff}");
+        assert_eq!(Source::page(&data, 3, true).unwrap().dimensions(), (75, 50));
+
+        let gray = DynamicImage::ImageLuma8(image.to_luma8());
+        let data = tiled.encode(&gray, None).unwrap();
+        let decoded = Source::new(&data, true).unwrap().decode().unwrap();
+        assert!(matches!(decoded, DynamicImage::ImageLuma8(_)));
+    }
+
+    #[test]
+    fn test_tiled_tiff_invalid_tile_size() {
+        let image = image::open("./fixtures/demo.jpg").unwrap();
+        let tiled = TiledTiff {
+            tile_size: 100,
+            ..Default::default()
+        };
+        assert!(tiled.encode(&image, None).is_err());
+    }
+}
@@ -0,0 +1,566 @@
+//! IIIF Image API 2.1 兼容层(IIIF Image API 2.1 compatibility.)
+//!
+//! 将 2.x 的请求参数解析为 3.0 的 `Region`/`Size`/`Rotation`/`Quality` 模型，
+//! 并根据同一份 `ImageInfo` 生成 2.1 格式的 `info.json`。
+//!
+//! Parses 2.x request parameters into the 3.0 model and emits 2.1 style `info.json`
+//! documents from the same `ImageInfo` data.
+//!
+//! [官方文档(Official Documentation)](https://iiif.io/api/image/2.1/)
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::IiifError;
+use crate::image::{Feature, IiifImage, ImageInfo, Profile, Quality, Size};
+
+/// Image API 2.x 的 JSON-LD 上下文
+pub const IIIF_IMAGE_2_CONTEXT: &str = "http://iiif.io/api/image/2/context.json";
+
+/// IIIF Image API 版本
+///
+/// The version of the IIIF Image API a request or document follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ApiVersion {
+    /// Image API 2.1
+    V2,
+    /// Image API 3.0
+    #[default]
+    V3,
+}
+
+impl IiifImage {
+    /// 解析 Image API 2.x 格式的 URL
+    ///
+    /// Parses an Image API 2.x request URL into the 3.0 model.
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::image::{IiifImage, Quality, Size};
+    /// use url::Url;
+    ///
+    /// let url = Url::parse("https://example.org/iiif/demo.jpg/full/full/0/native.jpg").unwrap();
+    /// let image = IiifImage::try_from_v2(url).unwrap();
+    /// assert_eq!(image.size, Size::Max);
+    /// assert_eq!(image.quality, Quality::Default);
+    /// ```
+    pub fn try_from_v2(url: Url) -> Result<Self, IiifError> {
+        Self::try_from_url(url, ApiVersion::V2)
+    }
+
+    /// 以 Image API 2.x 格式输出请求路径
+    ///
+    /// Formats the request as an Image API 2.x path, the counterpart of `Display`.
+    pub fn to_v2_string(&self) -> String {
+        format!(
+            "{}/{}/{}/{}/{}.{}",
+            super::url_encode(&self.identifier),
+            self.region,
+            self.size.to_v2_string(),
+            self.rotation,
+            self.quality,
+            self.format
+        )
+    }
+}
+
+impl Size {
+    /// 解析 Image API 2.x 的尺寸参数
+    ///
+    /// 2.x 中 `full` 与 `max` 等价，不支持 `^` 前缀；`pct:n` 允许超过 100，此时对应 3.0 的 `^pct:n`。
+    ///
+    /// Parses a 2.x size parameter. `full` is treated as `max`, the `^` prefix does not exist,
+    /// and `pct:n` above 100 maps to the 3.0 `^pct:n` form.
+    pub fn from_v2_str(s: &str) -> Result<Self, IiifError> {
+        let s_trimmed = s.trim().to_lowercase();
+        if s_trimmed == "full" {
+            return Ok(Size::Max);
+        }
+        if s_trimmed.starts_with('^') {
+            return Err(IiifError::BadRequest("Invalid size format".to_string()));
+        }
+        if let Some(pct) = s_trimmed.strip_prefix("pct:") {
+            let n: f32 = pct
+                .parse()
+                .map_err(|_| IiifError::BadRequest("Invalid size format".to_string()))?;
+            if n > 100.0 {
+                return Ok(Size::CPct { n });
+            }
+        }
+        s_trimmed.parse()
+    }
+
+    /// 服务支持 `sizeAboveFull` 时 2.1 尺寸参数对应的 3.0 形式
+    ///
+    /// 2.1 中 `w,`、`,h`、`w,h` 和 `!w,h` 在服务声明 `sizeAboveFull` 时允许放大，对应 3.0 的
+    /// `^w,`、`^,h`、`^w,h` 和 `^!w,h`；其余尺寸参数不变。
+    ///
+    /// The 3.0 form of a 2.1 size parameter served with `sizeAboveFull`. In 2.1, `w,`, `,h`,
+    /// `w,h` and `!w,h` may then scale above the full size, which 3.0 spells `^w,`, `^,h`,
+    /// `^w,h` and `^!w,h`. Other sizes are returned unchanged.
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::image::Size;
+    ///
+    /// let size = Size::from_v2_str("600,").unwrap();
+    /// assert_eq!(size.v2_upscaled(), Size::CW { w: 600 });
+    /// assert_eq!(Size::Max.v2_upscaled(), Size::Max);
+    /// ```
+    pub fn v2_upscaled(self) -> Self {
+        match self {
+            Size::W { w } => Size::CW { w },
+            Size::H { h } => Size::CH { h },
+            Size::WH { w, h } => Size::CWH { w, h },
+            Size::LWH { w, h } => Size::CLWH { w, h },
+            size => size,
+        }
+    }
+
+    /// 以 Image API 2.x 格式输出尺寸参数
+    ///
+    /// 2.x 中没有 `^` 前缀，放大由服务端能力（`sizeAboveFull`）决定。
+    ///
+    /// Formats the size as a 2.x parameter. 2.x has no `^` prefix; upscaling depends on
+    /// the `sizeAboveFull` capability of the server.
+    pub fn to_v2_string(&self) -> String {
+        match self {
+            Size::Max | Size::CMax => "full".to_string(),
+            Size::W { w } | Size::CW { w } => format!("{w},"),
+            Size::H { h } | Size::CH { h } => format!(",{h}"),
+            Size::Pct { n } | Size::CPct { n } => format!("pct:{n}"),
+            Size::WH { w, h } | Size::CWH { w, h } => format!("{w},{h}"),
+            Size::LWH { w, h } | Size::CLWH { w, h } => format!("!{w},{h}"),
+        }
+    }
+}
+
+impl Quality {
+    /// 解析 Image API 2.x 的画质参数，`native` 与 `default` 等价
+    ///
+    /// Parses a 2.x quality parameter, where `native` (from 1.x/2.0) means `default`.
+    pub fn from_v2_str(s: &str) -> Result<Self, IiifError> {
+        if s.trim().eq_ignore_ascii_case("native") {
+            return Ok(Quality::Default);
+        }
+        s.parse()
+    }
+}
+
+impl Feature {
+    /// Image API 2.x 中的功能名称
+    ///
+    /// The name of the feature in the Image API 2.x `supports` list.
+    pub fn v2_name(&self) -> &'static str {
+        match self {
+            Feature::BaseUriRedirect => "baseUriRedirect",
+            Feature::CanonicalLinkHeader => "canonicalLinkHeader",
+            Feature::Cors => "cors",
+            Feature::JsonldMediaType => "jsonldMediaType",
+            Feature::Mirroring => "mirroring",
+            Feature::ProfileLinkHeader => "profileLinkHeader",
+            Feature::RegionByPct => "regionByPct",
+            Feature::RegionByPx => "regionByPx",
+            Feature::RegionSquare => "regionSquare",
+            Feature::RotationArbitrary => "rotationArbitrary",
+            Feature::RotationBy90s => "rotationBy90s",
+            Feature::SizeByConfinedWh => "sizeByConfinedWh",
+            Feature::SizeByH => "sizeByH",
+            Feature::SizeByPct => "sizeByPct",
+            Feature::SizeByW => "sizeByW",
+            Feature::SizeByWh => "sizeByWh",
+            Feature::SizeUpscaling => "sizeAboveFull",
+        }
+    }
+}
+
+impl Profile {
+    /// Image API 2.x 的合规等级 URI
+    ///
+    /// The compliance level URI used in 2.x `profile` arrays.
+    pub fn v2_uri(&self) -> &'static str {
+        match self {
+            Profile::Level0 => "http://iiif.io/api/image/2/level0.json",
+            Profile::Level1 => "http://iiif.io/api/image/2/level1.json",
+            Profile::Level2 => "http://iiif.io/api/image/2/level2.json",
+        }
+    }
+}
+
+/// Image API 2.1 格式的图像信息
+///
+/// The image information document in Image API 2.1 form.
+///
+/// Example:
+/// ```
+/// use i3f::image::{ImageInfo, ImageInfoV2};
+///
+/// let info = ImageInfo {
+///     id: "https://example.org/iiif/demo.jpg".to_string(),
+///     width: 300,
+///     height: 200,
+///     ..Default::default()
+/// };
+/// let v2 = ImageInfoV2::from(&info);
+/// assert_eq!(v2.id, "https://example.org/iiif/demo.jpg");
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageInfoV2 {
+    /// 必须为 `http://iiif.io/api/image/2/context.json`
+    ///
+    /// Must be `http://iiif.io/api/image/2/context.json`.
+    #[serde(rename = "@context")]
+    pub context: String,
+
+    /// 图像的基础 URI
+    ///
+    /// The base URI of the image.
+    #[serde(rename = "@id")]
+    pub id: String,
+
+    /// 固定为 `http://iiif.io/api/image`
+    ///
+    /// Always `http://iiif.io/api/image`.
+    pub protocol: String,
+
+    /// 图像的宽度，以像素为单位。
+    ///
+    /// The width of the image, in pixels.
+    pub width: u32,
+
+    /// 图像的高度，以像素为单位。
+    ///
+    /// The height of the image, in pixels.
+    pub height: u32,
+
+    /// 合规等级 URI 以及额外能力描述
+    ///
+    /// The compliance level URI followed by descriptions of extra capabilities.
+    pub profile: Vec<ProfileEntryV2>,
+
+    /// 首选尺寸列表
+    ///
+    /// Preferred sizes for the full image.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sizes: Option<Vec<SizeInfoV2>>,
+
+    /// 支持的瓦片列表
+    ///
+    /// Supported tiles.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tiles: Option<Vec<TileInfoV2>>,
+
+    /// 许可或权利声明
+    ///
+    /// License or rights statement.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+}
+
+/// `profile` 数组中的一项：合规等级 URI 或能力描述对象
+///
+/// An entry of the 2.x `profile` array.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ProfileEntryV2 {
+    /// 合规等级 URI
+    Uri(String),
+    /// 额外能力描述
+    Description(ProfileDescriptionV2),
+}
+
+/// 2.x 的能力描述对象
+///
+/// Describes capabilities beyond the referenced compliance level.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileDescriptionV2 {
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub formats: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub qualities: Vec<String>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub supports: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_width: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_height: Option<u32>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_area: Option<u32>,
+}
+
+/// 2.x 的尺寸项
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SizeInfoV2 {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 2.x 的瓦片项
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TileInfoV2 {
+    pub width: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    pub scale_factors: Vec<u32>,
+}
+
+impl From<&ImageInfo> for ImageInfoV2 {
+    fn from(info: &ImageInfo) -> Self {
+        let description = ProfileDescriptionV2 {
+            formats: to_strings(info.extra_formats.iter().flatten()),
+            qualities: to_strings(info.extra_qualities.iter().flatten()),
+            supports: info
+                .extra_features
+                .iter()
+                .flatten()
+                .map(|feature| feature.v2_name().to_string())
+                .collect(),
+            max_width: info.max_width,
+            max_height: info.max_height,
+            max_area: info.max_area,
+        };
+        let mut profile = vec![ProfileEntryV2::Uri(info.profile.v2_uri().to_string())];
+        if description != ProfileDescriptionV2::default() {
+            profile.push(ProfileEntryV2::Description(description));
+        }
+
+        Self {
+            context: IIIF_IMAGE_2_CONTEXT.to_string(),
+            id: info.id.clone(),
+            protocol: info.protocol.clone(),
+            width: info.width,
+            height: info.height,
+            profile,
+            sizes: info.sizes.as_ref().map(|sizes| {
+                sizes
+                    .iter()
+                    .map(|size| SizeInfoV2 {
+                        width: size.width,
+                        height: size.height,
+                    })
+                    .collect()
+            }),
+            tiles: info.tiles.as_ref().map(|tiles| {
+                tiles
+                    .iter()
+                    .map(|tile| TileInfoV2 {
+                        width: tile.width,
+                        height: tile.height,
+                        scale_factors: tile
+                            .scale_factors
+                            .iter()
+                            .map(|&factor| u32::from(factor))
+                            .collect(),
+                    })
+                    .collect()
+            }),
+            license: info.rights.clone(),
+        }
+    }
+}
+
+impl ImageInfo {
+    /// 转换为 Image API 2.1 格式的图像信息
+    ///
+    /// Converts the image information into its Image API 2.1 form.
+    pub fn to_v2(&self) -> ImageInfoV2 {
+        ImageInfoV2::from(self)
+    }
+}
+
+fn to_strings<'a, T: std::fmt::Display + 'a>(values: impl Iterator<Item = &'a T>) -> Vec<String> {
+    values.map(|value| value.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::{Format, Region, Rotation, SizeInfo, TileInfo};
+
+    use super::*;
+
+    #[test]
+    fn test_size_from_v2_str() {
+        assert_eq!(Size::from_v2_str("full").unwrap(), Size::Max);
+        assert_eq!(Size::from_v2_str("max").unwrap(), Size::Max);
+        assert_eq!(Size::from_v2_str("150,").unwrap(), Size::W { w: 150 });
+        assert_eq!(Size::from_v2_str(",150").unwrap(), Size::H { h: 150 });
+        assert_eq!(
+            Size::from_v2_str("!225,100").unwrap(),
+            Size::LWH { w: 225, h: 100 }
+        );
+        assert_eq!(Size::from_v2_str("pct:50").unwrap(), Size::Pct { n: 50.0 });
+        assert_eq!(
+            Size::from_v2_str("pct:150").unwrap(),
+            Size::CPct { n: 150.0 }
+        );
+        assert!(Size::from_v2_str("^max").is_err());
+        assert!(Size::from_v2_str("^150,").is_err());
+        assert!(Size::from_v2_str("pct:abc").is_err());
+    }
+
+    #[test]
+    fn test_size_v2_upscaled() {
+        let cases = [
+            ("150,", Size::CW { w: 150 }),
+            (",150", Size::CH { h: 150 }),
+            ("150,100", Size::CWH { w: 150, h: 100 }),
+            ("!150,100", Size::CLWH { w: 150, h: 100 }),
+            ("pct:50", Size::Pct { n: 50.0 }),
+            ("full", Size::Max),
+        ];
+        for (s, expected) in cases {
+            let size = Size::from_v2_str(s).unwrap().v2_upscaled();
+            assert_eq!(size, expected, "{s}");
+            assert_eq!(size.to_v2_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_process_v2_upscaling() {
+        use crate::image::{ImageRequest, RequestParser, ServiceConfig};
+        use crate::storage::LocalStorage;
+
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let path = "/iiif/2/demo.jpg/full/!1024,1024/0/default.png";
+        let parser = RequestParser::new("/iiif/2").with_version(ApiVersion::V2);
+        let parse = |parser: RequestParser| match parser.parse(path).unwrap() {
+            ImageRequest::Image(image) => image,
+            request => panic!("expected an image request: {request:?}"),
+        };
+
+        // 声明 `sizeAboveFull` 的服务按 2.1 语义放大
+        let config = ServiceConfig::default();
+        let image = parse(parser.clone().with_service(&config));
+        let result = image.process_with(&storage, &config).unwrap();
+        let output = image::load_from_memory(&result.data).unwrap();
+        assert_eq!((output.width(), output.height()), (1024, 683));
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/full/^!1024,1024/").unwrap();
+        std::fs::remove_dir_all("./fixtures/out/demo.jpg/full/^1024,683/").unwrap();
+
+        // 不支持放大的服务仍拒绝超过原图的尺寸
+        let config = ServiceConfig::level2();
+        let image = parse(parser.with_service(&config));
+        assert!(matches!(
+            image.process_with(&storage, &config),
+            Err(IiifError::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_size_to_v2_string() {
+        assert_eq!(Size::Max.to_v2_string(), "full");
+        assert_eq!(Size::CW { w: 360 }.to_v2_string(), "360,");
+        assert_eq!(Size::CPct { n: 150.0 }.to_v2_string(), "pct:150");
+        assert_eq!(Size::LWH { w: 225, h: 100 }.to_v2_string(), "!225,100");
+    }
+
+    #[test]
+    fn test_quality_from_v2_str() {
+        assert_eq!(Quality::from_v2_str("native").unwrap(), Quality::Default);
+        assert_eq!(Quality::from_v2_str("default").unwrap(), Quality::Default);
+        assert_eq!(Quality::from_v2_str("gray").unwrap(), Quality::Gray);
+        assert!(Quality::from_v2_str("grey").is_err());
+    }
+
+    #[test]
+    fn test_try_from_v2() {
+        let url = Url::parse("https://example.org/iiif/2/abcd1234/10,20,30,40/full/!90/native.png")
+            .unwrap();
+        let image = IiifImage::try_from_v2(url).unwrap();
+        assert_eq!(image.identifier, "abcd1234");
+        assert_eq!(image.region, Region::Rect(10, 20, 30, 40));
+        assert_eq!(image.size, Size::Max);
+        assert_eq!(image.rotation, Rotation::MirrorDegrees(90.0));
+        assert_eq!(image.quality, Quality::Default);
+        assert_eq!(image.format, Format::Png);
+        assert_eq!(
+            image.to_string(),
+            "abcd1234/10,20,30,40/max/!90/default.png"
+        );
+        assert_eq!(
+            image.to_v2_string(),
+            "abcd1234/10,20,30,40/full/!90/default.png"
+        );
+
+        let url = Url::parse("https://example.org/iiif/abcd1234/full/^max/0/default.jpg").unwrap();
+        assert!(IiifImage::try_from_v2(url).is_err());
+    }
+
+    #[test]
+    fn test_image_info_to_v2() {
+        let info = ImageInfo {
+            id: "https://example.org/iiif/demo.jpg".to_string(),
+            profile: Profile::Level1,
+            width: 6000,
+            height: 4000,
+            max_width: Some(3000),
+            sizes: Some(vec![SizeInfo {
+                r#type: None,
+                width: 150,
+                height: 100,
+            }]),
+            tiles: Some(vec![TileInfo {
+                r#type: None,
+                scale_factors: vec![1, 2, 4],
+                width: 512,
+                height: None,
+            }]),
+            extra_formats: Some(vec![Format::Png]),
+            extra_qualities: Some(vec![Quality::Gray]),
+            extra_features: Some(vec![Feature::Mirroring, Feature::SizeUpscaling]),
+            rights: Some("http://rightsstatements.org/vocab/InC/1.0/".to_string()),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(info.to_v2()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "@context": "http://iiif.io/api/image/2/context.json",
+                "@id": "https://example.org/iiif/demo.jpg",
+                "protocol": "http://iiif.io/api/image",
+                "width": 6000,
+                "height": 4000,
+                "profile": [
+                    "http://iiif.io/api/image/2/level1.json",
+                    {
+                        "formats": ["png"],
+                        "qualities": ["gray"],
+                        "supports": ["mirroring", "sizeAboveFull"],
+                        "maxWidth": 3000
+                    }
+                ],
+                "sizes": [{"width": 150, "height": 100}],
+                "tiles": [{"width": 512, "scaleFactors": [1, 2, 4]}],
+                "license": "http://rightsstatements.org/vocab/InC/1.0/"
+            })
+        );
+
+        let v2: ImageInfoV2 = serde_json::from_value(json).unwrap();
+        assert_eq!(v2, info.to_v2());
+    }
+
+    #[test]
+    fn test_image_info_to_v2_level_only() {
+        let info = ImageInfo {
+            id: "https://example.org/iiif/demo.jpg".to_string(),
+            width: 300,
+            height: 200,
+            ..Default::default()
+        };
+        let v2 = info.to_v2();
+        assert_eq!(
+            v2.profile,
+            vec![ProfileEntryV2::Uri(
+                "http://iiif.io/api/image/2/level0.json".to_string()
+            )]
+        );
+    }
+}
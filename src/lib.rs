@@ -1,6 +1,10 @@
+pub mod client;
 mod error;
+pub mod export;
 pub mod image;
+pub mod pdf;
 pub mod presentation;
 pub mod storage;
+pub mod telemetry;
 
 pub use error::*;
@@ -0,0 +1,1297 @@
+//! PDF 输出(PDF output)
+//!
+//! `Format::Pdf` 的单页图像和整个 Manifest 的多页文档共用同一个 PDF 写入器。Manifest 中每个 Canvas
+//! 生成一页，painting 注解中的图像通过图像处理流程获取，`label`/`metadata`/`summary`/`requiredStatement`
+//! 写入文档信息，`structures` 中的 Range 生成书签。单页图像可通过 [`PdfOptions`] 设置物理页面尺寸、
+//! 标题和版权声明，或生成 PDF/A-2b 归档文档。
+//!
+//! A small PDF writer shared by single-image `Format::Pdf` responses and whole-manifest
+//! documents. Rendering a manifest produces one page per Canvas, painted with the images of
+//! its `painting` annotations fetched through the image pipeline, fills the document
+//! information from the descriptive properties and turns the `structures` Ranges into the
+//! outline (bookmarks). Single images use [`PdfOptions`] for the physical page size, title
+//! and rights statement, and can be written as PDF/A-2b archival documents.
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, GrayImage, ImageDecoder, ImageEncoder};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, StringFormat, dictionary};
+use md5::{Digest, Md5};
+use moxcms::ColorProfile;
+use url::Url;
+
+use crate::IiifError;
+use crate::image::{Format, IiifImage, ServiceConfig, keeps_gray};
+use crate::presentation::{Canvas, LangMap, Manifest, Range, RangeItem};
+use crate::storage::Storage;
+
+/// 嵌入 PDF 的图像
+///
+/// An image embedded in a PDF page: either JPEG data (`DCTDecode`) or raw samples that are
+/// Flate compressed when written, with the ICC profile of its color space if any.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddedImage {
+    /// 像素宽度
+    pub width: u32,
+
+    /// 像素高度
+    pub height: u32,
+
+    /// 是否为灰度图像（`DeviceGray`），否则为 RGB
+    pub gray: bool,
+
+    /// 每个分量的位数，二值图像为 1
+    pub bits_per_component: u8,
+
+    /// `data` 是否为 JPEG 数据，否则为按行排列的原始像素
+    pub jpeg: bool,
+
+    /// 图像数据
+    pub data: Vec<u8>,
+
+    /// `ICCBased` 色彩空间使用的配置文件
+    pub icc_profile: Option<Vec<u8>>,
+}
+
+impl EmbeddedImage {
+    /// 按图像内容选择嵌入方式：只含黑白两色的灰度图像以 1 位无损嵌入，`lossless` 时其余图像以
+    /// 8 位无损嵌入，否则编码为 JPEG
+    ///
+    /// Chooses how to embed an image: gray images containing only black and white (such as
+    /// `bitonal` outputs) are stored losslessly with 1 bit per pixel, other images are stored
+    /// losslessly with 8 bits per component when `lossless` is set and as JPEG otherwise.
+    /// Gray images stay gray unless an RGB profile has to be embedded, and images embedding
+    /// a gray profile are stored gray.
+    pub fn new(
+        image: &DynamicImage,
+        icc_profile: Option<&[u8]>,
+        lossless: bool,
+    ) -> Result<Self, IiifError> {
+        if let DynamicImage::ImageLuma8(luma) = image
+            && icc_profile.is_none()
+            && luma.iter().all(|&v| v == 0 || v == 255)
+        {
+            return Ok(Self::bitonal(luma));
+        }
+        if lossless {
+            Ok(Self::flate(image, icc_profile))
+        } else {
+            Self::encode(image, icc_profile)
+        }
+    }
+
+    /// 将图像编码为 JPEG
+    pub fn encode(image: &DynamicImage, icc_profile: Option<&[u8]>) -> Result<Self, IiifError> {
+        let gray = is_gray(image, icc_profile);
+        let (width, height) = (image.width(), image.height());
+        let (samples, color_type) = if gray {
+            (image.to_luma8().into_raw(), image::ExtendedColorType::L8)
+        } else {
+            (image.to_rgb8().into_raw(), image::ExtendedColorType::Rgb8)
+        };
+        let mut data = Vec::new();
+        JpegEncoder::new(&mut Cursor::new(&mut data))
+            .write_image(&samples, width, height, color_type)
+            .map_err(|e| {
+                IiifError::InternalServerError(format!("Failed to encode JPEG image: {e}"))
+            })?;
+        Ok(Self {
+            width,
+            height,
+            gray,
+            bits_per_component: 8,
+            jpeg: true,
+            data,
+            icc_profile: icc_profile.map(<[u8]>::to_vec),
+        })
+    }
+
+    /// 以 8 位原始像素无损嵌入
+    pub fn flate(image: &DynamicImage, icc_profile: Option<&[u8]>) -> Self {
+        let gray = is_gray(image, icc_profile);
+        let data = if gray {
+            image.to_luma8().into_raw()
+        } else {
+            image.to_rgb8().into_raw()
+        };
+        Self {
+            width: image.width(),
+            height: image.height(),
+            gray,
+            bits_per_component: 8,
+            jpeg: false,
+            data,
+            icc_profile: icc_profile.map(<[u8]>::to_vec),
+        }
+    }
+
+    /// 以每像素 1 位无损嵌入，低于 128 的像素为黑色
+    pub fn bitonal(image: &GrayImage) -> Self {
+        let row_bytes = (image.width() as usize).div_ceil(8);
+        let mut data = vec![0u8; row_bytes * image.height() as usize];
+        for (x, y, pixel) in image.enumerate_pixels() {
+            // DeviceGray 中 1 为白色
+            if pixel.0[0] >= 128 {
+                data[y as usize * row_bytes + x as usize / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        Self {
+            width: image.width(),
+            height: image.height(),
+            gray: true,
+            bits_per_component: 1,
+            jpeg: false,
+            data,
+            icc_profile: None,
+        }
+    }
+
+    /// 直接嵌入已有的 JPEG 数据，不重新编码
+    pub fn from_jpeg(data: Vec<u8>) -> Result<Self, IiifError> {
+        let decoder = image::ImageReader::with_format(Cursor::new(&data), image::ImageFormat::Jpeg)
+            .into_decoder()
+            .map_err(|e| IiifError::InternalServerError(e.to_string()))?;
+        let (width, height) = decoder.dimensions();
+        let gray = decoder.color_type() == image::ColorType::L8;
+        drop(decoder);
+        Ok(Self {
+            width,
+            height,
+            gray,
+            bits_per_component: 8,
+            jpeg: true,
+            data,
+            icc_profile: None,
+        })
+    }
+}
+
+/// 嵌入配置文件时按其色彩空间决定是否为灰度，否则灰度图像保持灰度
+fn is_gray(image: &DynamicImage, icc_profile: Option<&[u8]>) -> bool {
+    keeps_gray(image, icc_profile)
+}
+
+/// 图像在页面中的位置，坐标单位为点，原点在页面左上角
+///
+/// An image placed on a page. Coordinates are in points from the top-left corner of the
+/// page, like Canvas coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placement {
+    pub image: EmbeddedImage,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// 书签
+///
+/// An outline (bookmark) item pointing to a 0-based page index.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OutlineItem {
+    pub title: String,
+    pub page: Option<usize>,
+    pub children: Vec<OutlineItem>,
+}
+
+/// 纸张尺寸，单位为点（1/72 英寸），按纵向给出
+///
+/// A paper size in points (1/72 inch), given in portrait orientation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Paper {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Paper {
+    pub const A3: Paper = Paper {
+        width: 841.89,
+        height: 1190.55,
+    };
+    pub const A4: Paper = Paper {
+        width: 595.28,
+        height: 841.89,
+    };
+    pub const A5: Paper = Paper {
+        width: 419.53,
+        height: 595.28,
+    };
+    pub const LETTER: Paper = Paper {
+        width: 612.0,
+        height: 792.0,
+    };
+    pub const LEGAL: Paper = Paper {
+        width: 612.0,
+        height: 1008.0,
+    };
+}
+
+/// 图像在纸张上的缩放方式
+///
+/// How an image is scaled onto a paper page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PaperFit {
+    /// 完整显示图像，居中并保留空白
+    ///
+    /// Shows the whole image centred on the page, leaving margins.
+    #[default]
+    Fit,
+
+    /// 铺满页面，居中并裁去超出部分
+    ///
+    /// Covers the whole page centred on it, cropping what overflows.
+    Fill,
+}
+
+/// PDF 页面尺寸
+///
+/// The physical size of the page of a `Format::Pdf` response.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PageSize {
+    /// 每个像素对应 1 点
+    ///
+    /// One point per pixel.
+    #[default]
+    Pixels,
+
+    /// 按分辨率（每英寸像素数）计算页面尺寸
+    ///
+    /// Page size computed from a resolution in pixels per inch.
+    Dpi(f64),
+
+    /// 固定纸张尺寸，纸张方向随图像方向自动调整
+    ///
+    /// A fixed paper size, turned to landscape for landscape images.
+    Paper(Paper, PaperFit),
+}
+
+/// 页面尺寸 `(width, height)` 以及图像在页面中的位置 `(x, y, width, height)`，单位为点
+///
+/// The page size and the rectangle of the image on it, in points.
+pub type PageLayout = ((f64, f64), (f64, f64, f64, f64));
+
+impl PageSize {
+    /// 计算页面尺寸以及图像在页面中的位置 `(x, y, width, height)`，单位为点
+    ///
+    /// Computes the page size and the rectangle `(x, y, width, height)` of an image of
+    /// `width` x `height` pixels on it, in points from the top-left corner. A resolution that
+    /// is not a finite positive number fails with `IiifError::InternalServerError`.
+    ///
+    /// Example:
+    /// ```
+    /// use i3f::pdf::{PageSize, Paper, PaperFit};
+    ///
+    /// let layout = PageSize::Dpi(300.0).layout(600, 300).unwrap();
+    /// assert_eq!(layout, ((144.0, 72.0), (0.0, 0.0, 144.0, 72.0)));
+    /// assert!(PageSize::Dpi(0.0).layout(600, 300).is_err());
+    ///
+    /// let ((width, height), (x, y, w, h)) =
+    ///     PageSize::Paper(Paper::LETTER, PaperFit::Fit).layout(400, 200).unwrap();
+    /// assert_eq!((width, height), (792.0, 612.0));
+    /// assert_eq!((x, y, w, h), (0.0, 108.0, 792.0, 396.0));
+    /// ```
+    pub fn layout(&self, width: u32, height: u32) -> Result<PageLayout, IiifError> {
+        let (width, height) = (width as f64, height as f64);
+        Ok(match *self {
+            PageSize::Pixels => ((width, height), (0.0, 0.0, width, height)),
+            PageSize::Dpi(dpi) => {
+                // 分辨率必须为有限正数，否则页面尺寸无意义
+                if !dpi.is_finite() || dpi <= 0.0 {
+                    return Err(IiifError::InternalServerError(format!(
+                        "Invalid PDF resolution: {dpi} dpi"
+                    )));
+                }
+                let (width, height) = (width * 72.0 / dpi, height * 72.0 / dpi);
+                ((width, height), (0.0, 0.0, width, height))
+            }
+            PageSize::Paper(paper, fit) => {
+                let (page_width, page_height) = if width > height {
+                    (paper.height, paper.width)
+                } else {
+                    (paper.width, paper.height)
+                };
+                let (sx, sy) = (page_width / width, page_height / height);
+                let scale = match fit {
+                    PaperFit::Fit => sx.min(sy),
+                    PaperFit::Fill => sx.max(sy),
+                };
+                let (w, h) = (width * scale, height * scale);
+                (
+                    (page_width, page_height),
+                    ((page_width - w) / 2.0, (page_height - h) / 2.0, w, h),
+                )
+            }
+        })
+    }
+}
+
+/// `Format::Pdf` 输出选项
+///
+/// Options of `Format::Pdf` responses, set through `ServiceConfig::pdf`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PdfOptions {
+    /// 页面尺寸
+    ///
+    /// The physical page size.
+    pub page_size: PageSize,
+
+    /// 文档标题
+    ///
+    /// The document title.
+    pub title: Option<String>,
+
+    /// 版权声明，写入文档信息的 `Rights` 条目
+    ///
+    /// The rights statement, written to the `Rights` entry of the document information.
+    pub rights: Option<String>,
+
+    /// 是否以无损方式嵌入灰度和彩色图像，二值图像始终无损嵌入
+    ///
+    /// Whether gray and color images are embedded losslessly (Flate) instead of as JPEG.
+    /// Black and white images are always embedded losslessly with 1 bit per pixel.
+    pub lossless: bool,
+
+    /// 是否生成 PDF/A-2b 归档文档
+    ///
+    /// Whether a PDF/A-2b archival document is written, see [`PdfWriter::set_pdfa`].
+    pub pdfa: bool,
+}
+
+impl PdfOptions {
+    /// 生成单页 PDF
+    ///
+    /// Writes a single-page PDF of `image` with these options.
+    pub fn render(
+        &self,
+        image: &DynamicImage,
+        icc_profile: Option<&[u8]>,
+    ) -> Result<Vec<u8>, IiifError> {
+        let image = EmbeddedImage::new(image, icc_profile, self.lossless)?;
+        let ((page_width, page_height), (x, y, width, height)) =
+            self.page_size.layout(image.width, image.height)?;
+        let mut writer = PdfWriter::new();
+        writer.set_pdfa(self.pdfa);
+        if let Some(title) = &self.title {
+            writer.set_info("Title", title);
+        }
+        if let Some(rights) = &self.rights {
+            writer.set_info("Rights", rights);
+        }
+        writer.add_page(
+            page_width,
+            page_height,
+            vec![Placement {
+                image,
+                x,
+                y,
+                width,
+                height,
+            }],
+        );
+        writer.save()
+    }
+}
+
+/// PDF 写入器
+///
+/// Builds a PDF document page by page.
+///
+/// Example:
+/// ```
+/// use i3f::pdf::{EmbeddedImage, Placement, PdfWriter};
+///
+/// let image = image::open("./fixtures/demo.jpg").unwrap();
+/// let image = EmbeddedImage::encode(&image, None).unwrap();
+/// let mut writer = PdfWriter::new();
+/// writer.set_info("Title", "Demo");
+/// writer.add_page(300.0, 200.0, vec![Placement { image, x: 0.0, y: 0.0, width: 300.0, height: 200.0 }]);
+/// let pdf = writer.save().unwrap();
+/// assert!(pdf.starts_with(b"%PDF"));
+/// ```
+#[derive(Debug)]
+pub struct PdfWriter {
+    doc: Document,
+    pages_id: ObjectId,
+    pages: Vec<ObjectId>,
+    info: Vec<(String, String)>,
+    outline: Vec<OutlineItem>,
+    pdfa: bool,
+}
+
+impl Default for PdfWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PdfWriter {
+    pub fn new() -> Self {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        Self {
+            doc,
+            pages_id,
+            pages: Vec::new(),
+            info: Vec::new(),
+            outline: Vec::new(),
+            pdfa: false,
+        }
+    }
+
+    /// 添加一页，返回页码（从 0 开始）
+    ///
+    /// Adds a `width` x `height` points page painted with `images` and returns its index.
+    pub fn add_page(&mut self, width: f64, height: f64, images: Vec<Placement>) -> usize {
+        let mut content = String::new();
+        let mut xobjects = Dictionary::new();
+        for (i, placement) in images.into_iter().enumerate() {
+            let name = format!("Im{}", i + 1);
+            // PDF 坐标原点在左下角
+            let y = height - placement.y - placement.height;
+            content.push_str(&format!(
+                "q\n{} 0 0 {} {} {y} cm\n/{name} Do\nQ\n",
+                placement.width, placement.height, placement.x
+            ));
+            let image_id = self.add_image(placement.image);
+            xobjects.set(name, image_id);
+        }
+        let content_id = self
+            .doc
+            .add_object(Stream::new(dictionary! {}, content.into_bytes()));
+        let page = dictionary! {
+            "Type" => "Page",
+            "Parent" => self.pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), width.into(), height.into()],
+            "Resources" => dictionary! {
+                "XObject" => xobjects,
+            },
+            "Contents" => content_id,
+        };
+        self.pages.push(self.doc.add_object(page));
+        self.pages.len() - 1
+    }
+
+    fn add_image(&mut self, image: EmbeddedImage) -> ObjectId {
+        let device = if image.gray {
+            "DeviceGray"
+        } else {
+            "DeviceRGB"
+        };
+        let mut image_dict = dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => image.width as i64,
+            "Height" => image.height as i64,
+            "ColorSpace" => device,
+            "BitsPerComponent" => image.bits_per_component as i64,
+        };
+        if image.jpeg {
+            image_dict.set("Filter", "DCTDecode"); // JPEG 使用 DCTDecode
+        }
+        // 嵌入配置文件时使用 ICCBased 色彩空间
+        if let Some(icc_profile) = image.icc_profile {
+            let icc_dict = dictionary! {
+                "N" => if image.gray { 1 } else { 3 },
+                "Alternate" => device,
+            };
+            let icc_id = self.doc.add_object(Stream::new(icc_dict, icc_profile));
+            image_dict.set(
+                "ColorSpace",
+                vec![
+                    Object::Name(b"ICCBased".to_vec()),
+                    Object::Reference(icc_id),
+                ],
+            );
+        }
+        let mut stream = Stream::new(image_dict, image.data);
+        if !image.jpeg {
+            // 原始像素使用 FlateDecode 无损压缩
+            let _ = stream.compress();
+        }
+        self.doc.add_object(stream)
+    }
+
+    /// 设置文档信息字典中的条目，如 `Title`、`Author`、`Subject`
+    pub fn set_info(&mut self, key: &str, value: &str) {
+        match self.info.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.info.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    /// 生成 PDF/A-2b 文档
+    ///
+    /// Writes a PDF/A-2b (ISO 19005-2, level B) document: PDF 1.7 with a binary header
+    /// comment, XMP metadata mirroring the document information, an sRGB output intent and
+    /// a file identifier. The writer never produces encryption, LZW, JavaScript, actions or
+    /// embedded files, which PDF/A disallows.
+    pub fn set_pdfa(&mut self, pdfa: bool) {
+        self.pdfa = pdfa;
+    }
+
+    /// 设置书签
+    pub fn set_outline(&mut self, outline: Vec<OutlineItem>) {
+        self.outline = outline;
+    }
+
+    /// 写出 PDF 文档
+    pub fn save(mut self) -> Result<Vec<u8>, IiifError> {
+        let kids = self
+            .pages
+            .iter()
+            .map(|&id| Object::Reference(id))
+            .collect::<Vec<_>>();
+        let pages = dictionary! {
+            "Type" => "Pages",
+            "Count" => kids.len() as i64,
+            "Kids" => kids,
+        };
+        self.doc
+            .objects
+            .insert(self.pages_id, Object::Dictionary(pages));
+
+        let mut catalog = dictionary! {
+            "Type" => "Catalog",
+            "Pages" => self.pages_id,
+        };
+        if !self.outline.is_empty() {
+            let outlines_id = self.doc.new_object_id();
+            let outline = std::mem::take(&mut self.outline);
+            let (first, last, count) = self.add_outline_items(&outline, outlines_id);
+            let outlines = dictionary! {
+                "Type" => "Outlines",
+                "First" => first,
+                "Last" => last,
+                "Count" => count,
+            };
+            self.doc
+                .objects
+                .insert(outlines_id, Object::Dictionary(outlines));
+            catalog.set("Outlines", outlines_id);
+            catalog.set("PageMode", "UseOutlines");
+        }
+        if self.pdfa {
+            self.add_pdfa(&mut catalog)?;
+        }
+        let catalog_id = self.doc.add_object(catalog);
+        self.doc.trailer.set("Root", catalog_id);
+        if !self.info.is_empty() {
+            let mut info = Dictionary::new();
+            for (key, value) in &self.info {
+                info.set(key.as_str(), text_string(value));
+            }
+            let info_id = self.doc.add_object(info);
+            self.doc.trailer.set("Info", info_id);
+        }
+
+        let mut bytes = Vec::new();
+        self.doc.save_to(&mut bytes).map_err(|e| {
+            IiifError::InternalServerError(format!("Failed to save PDF document: {e}"))
+        })?;
+        Ok(bytes)
+    }
+
+    /// 添加 PDF/A 所需的 XMP 元数据、输出意图和文件标识
+    fn add_pdfa(&mut self, catalog: &mut Dictionary) -> Result<(), IiifError> {
+        self.doc.version = "1.7".to_string();
+
+        // 元数据流不能压缩，以便归档工具直接读取
+        let xmp = xmp_metadata(&self.info);
+        let mut metadata = Stream::new(
+            dictionary! {
+                "Type" => "Metadata",
+                "Subtype" => "XML",
+            },
+            xmp.as_bytes().to_vec(),
+        );
+        metadata.allows_compression = false;
+        catalog.set("Metadata", self.doc.add_object(metadata));
+
+        let srgb = srgb_profile()?;
+        let profile_id = self
+            .doc
+            .add_object(Stream::new(dictionary! { "N" => 3 }, srgb));
+        let output_intent = dictionary! {
+            "Type" => "OutputIntent",
+            "S" => "GTS_PDFA1",
+            "OutputConditionIdentifier" => Object::string_literal(SRGB_CONDITION),
+            "Info" => Object::string_literal(SRGB_CONDITION),
+            "DestOutputProfile" => profile_id,
+        };
+        catalog.set(
+            "OutputIntents",
+            vec![self.doc.add_object(output_intent).into()],
+        );
+
+        // 文件标识为文档内容的 MD5 摘要，相同输入在任何平台和工具链下得到相同输出
+        let mut hasher = Md5::new();
+        hasher.update(xmp.as_bytes());
+        for object in self.doc.objects.values() {
+            if let Object::Stream(stream) = object {
+                hasher.update(&stream.content);
+            }
+        }
+        let id = hasher.finalize().to_vec();
+        self.doc.trailer.set(
+            "ID",
+            vec![
+                Object::String(id.clone(), StringFormat::Hexadecimal),
+                Object::String(id, StringFormat::Hexadecimal),
+            ],
+        );
+        Ok(())
+    }
+
+    /// 写入同一层级的书签，返回第一个、最后一个书签以及包含子孙在内的书签总数
+    fn add_outline_items(
+        &mut self,
+        items: &[OutlineItem],
+        parent: ObjectId,
+    ) -> (ObjectId, ObjectId, i64) {
+        let ids = items
+            .iter()
+            .map(|_| self.doc.new_object_id())
+            .collect::<Vec<_>>();
+        let mut count = ids.len() as i64;
+        for (i, item) in items.iter().enumerate() {
+            let mut dict = dictionary! {
+                "Title" => text_string(&item.title),
+                "Parent" => parent,
+            };
+            if i > 0 {
+                dict.set("Prev", ids[i - 1]);
+            }
+            if i + 1 < ids.len() {
+                dict.set("Next", ids[i + 1]);
+            }
+            if let Some(&page_id) = item.page.and_then(|page| self.pages.get(page)) {
+                dict.set("Dest", vec![page_id.into(), "Fit".into()]);
+            }
+            if !item.children.is_empty() {
+                let (first, last, children) = self.add_outline_items(&item.children, ids[i]);
+                dict.set("First", first);
+                dict.set("Last", last);
+                dict.set("Count", children);
+                count += children;
+            }
+            self.doc.objects.insert(ids[i], Object::Dictionary(dict));
+        }
+        (ids[0], ids[ids.len() - 1], count)
+    }
+}
+
+const SRGB_CONDITION: &str = "sRGB IEC61966-2.1";
+
+/// 输出意图使用的 sRGB 配置文件。moxcms 在文件头写入编码时刻，这里改为固定日期，
+/// 使文件标识只取决于文档内容
+fn srgb_profile() -> Result<Vec<u8>, IiifError> {
+    let mut srgb = ColorProfile::new_srgb().encode().map_err(|e| {
+        IiifError::InternalServerError(format!("Failed to encode sRGB profile: {e}"))
+    })?;
+    // 文件头第 24-35 字节依次为年、月、日、时、分、秒，均为大端 u16
+    let date = [2000u16, 1, 1, 0, 0, 0].map(u16::to_be_bytes);
+    srgb[24..36].copy_from_slice(date.as_flattened());
+    Ok(srgb)
+}
+
+/// 生成与文档信息一致的 XMP 元数据包，声明 PDF/A-2b 一致性
+fn xmp_metadata(info: &[(String, String)]) -> String {
+    let mut properties = String::new();
+    for (key, value) in info {
+        let value = xml_escape(value);
+        let property = match key.as_str() {
+            "Title" => format!(
+                "<dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">{value}</rdf:li></rdf:Alt></dc:title>"
+            ),
+            "Author" => {
+                format!("<dc:creator><rdf:Seq><rdf:li>{value}</rdf:li></rdf:Seq></dc:creator>")
+            }
+            "Subject" => format!(
+                "<dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{value}</rdf:li></rdf:Alt></dc:description>"
+            ),
+            "Rights" => format!(
+                "<dc:rights><rdf:Alt><rdf:li xml:lang=\"x-default\">{value}</rdf:li></rdf:Alt></dc:rights>"
+            ),
+            "Keywords" => format!("<pdf:Keywords>{value}</pdf:Keywords>"),
+            "Producer" => format!("<pdf:Producer>{value}</pdf:Producer>"),
+            "Creator" => format!("<xmp:CreatorTool>{value}</xmp:CreatorTool>"),
+            _ => continue,
+        };
+        properties.push_str("\n      ");
+        properties.push_str(&property);
+    }
+    format!(
+        r#"<?xpacket begin="{bom}" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about=""
+        xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/"
+        xmlns:dc="http://purl.org/dc/elements/1.1/"
+        xmlns:pdf="http://ns.adobe.com/pdf/1.3/"
+        xmlns:xmp="http://ns.adobe.com/xap/1.0/">
+      <pdfaid:part>2</pdfaid:part>
+      <pdfaid:conformance>B</pdfaid:conformance>{properties}
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+        bom = '\u{FEFF}'
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// PDF 文本字符串，非 ASCII 文本使用带 BOM 的 UTF-16BE 编码
+fn text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::string_literal(text);
+    }
+    let mut bytes = vec![0xFE, 0xFF];
+    for unit in text.encode_utf16() {
+        bytes.extend(unit.to_be_bytes());
+    }
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+/// 将 Manifest 渲染为多页 PDF
+///
+/// Renders a [`Manifest`] as a PDF with one page per Canvas. Canvas dimensions become the
+/// page size in points, and each `painting` annotation with an `Image` body is requested as
+/// JPEG through [`IiifImage::process_with`] and placed on the annotation target
+/// (`#xywh=` fragments are honoured). The document information is filled as follows:
+///
+/// - `Title`: the manifest `label`
+/// - `Author`: the `metadata` entry labelled `Author` or `Creator`
+/// - `Subject`: the manifest `summary`
+/// - `Rights`: the `requiredStatement` as `label: value`
+///
+/// The `structures` Ranges become the outline, each item pointing to the first Canvas of
+/// its Range.
+#[derive(Debug, Clone, Default)]
+pub struct ManifestPdf {
+    /// 获取图像时使用的服务配置
+    pub config: ServiceConfig,
+
+    /// 多语言文本优先使用的语言，未设置时依次使用 `none`、`en` 和其他语言
+    pub language: Option<String>,
+}
+
+impl ManifestPdf {
+    /// 渲染 Manifest
+    pub fn render(&self, manifest: &Manifest, storage: &dyn Storage) -> Result<Vec<u8>, IiifError> {
+        let mut writer = PdfWriter::new();
+        let mut pages = HashMap::new();
+        for canvas in &manifest.items {
+            let page = self.render_canvas(&mut writer, canvas, storage)?;
+            pages.insert(canvas.id.as_str(), page);
+        }
+
+        if let Some(title) = self.text(&manifest.label) {
+            writer.set_info("Title", &title);
+        }
+        let author = manifest.metadata.iter().flatten().find(|entry| {
+            entry.label.values().flatten().any(|label| {
+                label.eq_ignore_ascii_case("author") || label.eq_ignore_ascii_case("creator")
+            })
+        });
+        if let Some(author) = author.and_then(|entry| self.text(&entry.value)) {
+            writer.set_info("Author", &author);
+        }
+        if let Some(subject) = manifest.summary.as_ref().and_then(|s| self.text(s)) {
+            writer.set_info("Subject", &subject);
+        }
+        if let Some(statement) = &manifest.required_statement {
+            let label = self.text(&statement.label).unwrap_or_default();
+            let value = self.text(&statement.value).unwrap_or_default();
+            writer.set_info("Rights", &format!("{label}: {value}"));
+        }
+
+        let outline = manifest
+            .structures
+            .iter()
+            .flatten()
+            .map(|range| self.outline_item(range, &pages))
+            .collect();
+        writer.set_outline(outline);
+        writer.save()
+    }
+
+    fn render_canvas(
+        &self,
+        writer: &mut PdfWriter,
+        canvas: &Canvas,
+        storage: &dyn Storage,
+    ) -> Result<usize, IiifError> {
+        let mut placements = Vec::new();
+        let annotations = canvas
+            .items
+            .iter()
+            .flat_map(|page| &page.items)
+            .filter(|annotation| annotation.motivation == "painting")
+            .filter(|annotation| annotation.body.r#type == "Image");
+        for annotation in annotations {
+            let url = Url::parse(&annotation.body.id)
+                .map_err(|e| IiifError::InvalidIiifURL(format!("{}: {e}", annotation.body.id)))?;
+            let mut request = IiifImage::try_from(url)?;
+            request.format = Format::Jpg;
+            let result = request.process_with(storage, &self.config)?;
+            let image = EmbeddedImage::from_jpeg(result.data)?;
+            let (x, y, width, height) = target_rect(&annotation.target).unwrap_or((
+                0.0,
+                0.0,
+                canvas.width.unwrap_or(image.width) as f64,
+                canvas.height.unwrap_or(image.height) as f64,
+            ));
+            placements.push(Placement {
+                image,
+                x,
+                y,
+                width,
+                height,
+            });
+        }
+
+        let (width, height) = match (canvas.width, canvas.height, placements.first()) {
+            (Some(width), Some(height), _) => (width as f64, height as f64),
+            (_, _, Some(first)) => (first.width, first.height),
+            _ => {
+                return Err(IiifError::BadRequest(format!(
+                    "Canvas {} has neither dimensions nor images",
+                    canvas.id
+                )));
+            }
+        };
+        Ok(writer.add_page(width, height, placements))
+    }
+
+    fn outline_item(&self, range: &Range, pages: &HashMap<&str, usize>) -> OutlineItem {
+        let mut item = OutlineItem {
+            title: self.text(&range.label).unwrap_or_default(),
+            ..Default::default()
+        };
+        for child in range.items.iter().flatten() {
+            match child {
+                RangeItem::Canvas(canvas) => {
+                    // Canvas 的 id 可能带有 `#t=` 或 `#xywh=` 片段
+                    let id = canvas.id.split('#').next().unwrap_or_default();
+                    item.page = item.page.or_else(|| pages.get(id).copied());
+                }
+                RangeItem::Range(range) => {
+                    let child = self.outline_item(range, pages);
+                    item.page = item.page.or(child.page);
+                    item.children.push(child);
+                }
+            }
+        }
+        item
+    }
+
+    /// 按语言偏好取出多语言文本，同一语言的多个值以 `; ` 连接
+    fn text(&self, map: &LangMap) -> Option<String> {
+        let language = self
+            .language
+            .as_deref()
+            .filter(|language| map.contains_key(*language))
+            .or(["none", "en"].into_iter().find(|l| map.contains_key(*l)))
+            .or(map.keys().map(String::as_str).min())?;
+        let values = &map[language];
+        (!values.is_empty()).then(|| values.join("; "))
+    }
+}
+
+/// 解析注解目标中的 `#xywh=x,y,w,h` 片段
+fn target_rect(target: &str) -> Option<(f64, f64, f64, f64)> {
+    let (_, fragment) = target.split_once("#xywh=")?;
+    let fragment = fragment.strip_prefix("pixel:").unwrap_or(fragment);
+    let values = fragment
+        .split(',')
+        .map(|value| value.parse::<f64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    match values[..] {
+        [x, y, width, height] => Some((x, y, width, height)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::presentation::{Annotation, AnnotationPage, Metadata, Resource};
+    use crate::storage::LocalStorage;
+
+    use super::*;
+
+    fn lang(language: &str, value: &str) -> LangMap {
+        LangMap::from([(language.to_string(), vec![value.to_string()])])
+    }
+
+    fn canvas(id: &str, image: &str, target: &str, size: Option<(u32, u32)>) -> Canvas {
+        Canvas {
+            id: id.to_string(),
+            width: size.map(|s| s.0),
+            height: size.map(|s| s.1),
+            items: vec![AnnotationPage {
+                items: vec![Annotation {
+                    motivation: "painting".to_string(),
+                    target: format!("{id}{target}"),
+                    body: Resource {
+                        id: image.to_string(),
+                        r#type: "Image".to_string(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn range(label: &str, items: Vec<RangeItem>) -> RangeItem {
+        RangeItem::Range(Box::new(Range {
+            label: lang("en", label),
+            items: Some(items),
+            ..Default::default()
+        }))
+    }
+
+    fn canvas_ref(id: &str) -> RangeItem {
+        RangeItem::Canvas(Box::new(Canvas {
+            id: id.to_string(),
+            ..Default::default()
+        }))
+    }
+
+    fn manifest() -> Manifest {
+        let base = "https://example.org/iiif/demo.jpg";
+        let RangeItem::Range(contents) = range(
+            "Contents",
+            vec![
+                range("Cover", vec![canvas_ref("https://example.org/canvas/1")]),
+                range(
+                    "Chapter 1",
+                    vec![
+                        range(
+                            "Section 1.1",
+                            vec![canvas_ref("https://example.org/canvas/2")],
+                        ),
+                        canvas_ref("https://example.org/canvas/2"),
+                    ],
+                ),
+            ],
+        ) else {
+            unreachable!()
+        };
+        Manifest {
+            label: lang("zh", "示例图册"),
+            summary: Some(lang("en", "A demo volume")),
+            metadata: Some(vec![Metadata {
+                label: lang("en", "Author"),
+                value: lang("none", "Jane Doe"),
+            }]),
+            required_statement: Some(Metadata {
+                label: lang("en", "Attribution"),
+                value: lang("en", "Example Museum"),
+            }),
+            structures: Some(vec![*contents]),
+            items: vec![
+                canvas(
+                    "https://example.org/canvas/1",
+                    &format!("{base}/full/max/0/default.jpg"),
+                    "",
+                    None,
+                ),
+                canvas(
+                    "https://example.org/canvas/2",
+                    &format!("{base}/full/max/0/default.jpg"),
+                    "#xywh=100,50,300,200",
+                    Some((600, 400)),
+                ),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_manifest() {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let pdf = ManifestPdf::default()
+            .render(&manifest(), &storage)
+            .unwrap();
+        let doc = Document::load_mem(&pdf).unwrap();
+
+        let pages = doc.get_pages();
+        assert_eq!(pages.len(), 2);
+        let media_box = |page: u32| {
+            let page = doc.get_dictionary(pages[&page]).unwrap();
+            let media_box = page.get(b"MediaBox").unwrap().as_array().unwrap();
+            media_box
+                .iter()
+                .map(|v| v.as_float().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(media_box(1), vec![0.0, 0.0, 300.0, 200.0]);
+        assert_eq!(media_box(2), vec![0.0, 0.0, 600.0, 400.0]);
+        let content = doc.get_page_content(pages[&2]).unwrap();
+        assert!(
+            String::from_utf8(content)
+                .unwrap()
+                .contains("300 0 0 200 100 150 cm")
+        );
+
+        let info_id = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        let info = doc.get_dictionary(info_id).unwrap();
+        let info_text = |key: &[u8]| {
+            let text = info.get(key).unwrap();
+            lopdf::decode_text_string(text).unwrap()
+        };
+        assert_eq!(info_text(b"Title"), "示例图册");
+        assert_eq!(info_text(b"Author"), "Jane Doe");
+        assert_eq!(info_text(b"Subject"), "A demo volume");
+        assert_eq!(info_text(b"Rights"), "Attribution: Example Museum");
+
+        let catalog = doc.catalog().unwrap();
+        let outlines = catalog.get(b"Outlines").unwrap().as_reference().unwrap();
+        let outlines = doc.get_dictionary(outlines).unwrap();
+        assert_eq!(outlines.get(b"Count").unwrap().as_i64().unwrap(), 4);
+        let contents = outlines.get(b"First").unwrap().as_reference().unwrap();
+        let contents = doc.get_dictionary(contents).unwrap();
+        let chapter = contents.get(b"Last").unwrap().as_reference().unwrap();
+        let chapter = doc.get_dictionary(chapter).unwrap();
+        assert_eq!(
+            lopdf::decode_text_string(chapter.get(b"Title").unwrap()).unwrap(),
+            "Chapter 1"
+        );
+        let dest = chapter.get(b"Dest").unwrap().as_array().unwrap();
+        assert_eq!(dest[0].as_reference().unwrap(), pages[&2]);
+    }
+
+    #[test]
+    fn test_render_manifest_error() {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let mut manifest = manifest();
+        manifest.items[0].items[0].items[0].body.id = "demo.jpg".to_string();
+        assert!(ManifestPdf::default().render(&manifest, &storage).is_err());
+
+        manifest.items = vec![Canvas::default()];
+        assert!(matches!(
+            ManifestPdf::default().render(&manifest, &storage),
+            Err(IiifError::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_page_size_layout() {
+        let (page, rect) = PageSize::Pixels.layout(300, 200).unwrap();
+        assert_eq!((page, rect), ((300.0, 200.0), (0.0, 0.0, 300.0, 200.0)));
+
+        // 纵向图像铺满 A4，左右两侧超出页面
+        let ((width, height), (x, y, w, h)) = PageSize::Paper(Paper::A4, PaperFit::Fill)
+            .layout(100, 100)
+            .unwrap();
+        assert_eq!((width, height), (Paper::A4.width, Paper::A4.height));
+        assert!((w - Paper::A4.height).abs() < 1e-9 && w == h);
+        assert!(x < 0.0 && y.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_page_size_invalid_dpi() {
+        for dpi in [0.0, -72.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                PageSize::Dpi(dpi).layout(300, 200),
+                Err(IiifError::InternalServerError(_))
+            ));
+            let options = PdfOptions {
+                page_size: PageSize::Dpi(dpi),
+                ..Default::default()
+            };
+            let image = DynamicImage::new_rgb8(30, 20);
+            assert!(options.render(&image, None).is_err(), "{dpi}");
+        }
+    }
+
+    #[test]
+    fn test_pdf_options() {
+        let options = PdfOptions {
+            page_size: PageSize::Dpi(144.0),
+            title: Some("Demo".to_string()),
+            rights: Some("CC BY 4.0".to_string()),
+            ..Default::default()
+        };
+        let mut bitonal = GrayImage::new(200, 100);
+        for (x, _, pixel) in bitonal.enumerate_pixels_mut() {
+            pixel.0[0] = if x < 8 { 0 } else { 255 };
+        }
+        let pdf = options
+            .render(&DynamicImage::ImageLuma8(bitonal), None)
+            .unwrap();
+        let doc = Document::load_mem(&pdf).unwrap();
+
+        let info_id = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        let info = doc.get_dictionary(info_id).unwrap();
+        assert_eq!(info.get(b"Title").unwrap().as_str().unwrap(), b"Demo");
+        assert_eq!(info.get(b"Rights").unwrap().as_str().unwrap(), b"CC BY 4.0");
+
+        let pages = doc.get_pages();
+        let page = doc.get_dictionary(pages[&1]).unwrap();
+        let media_box = page.get(b"MediaBox").unwrap().as_array().unwrap();
+        assert_eq!(media_box[2].as_float().unwrap(), 100.0);
+        assert_eq!(media_box[3].as_float().unwrap(), 50.0);
+
+        // 二值图像以 1 位无损嵌入
+        let images = doc.get_page_images(pages[&1]).unwrap();
+        assert_eq!(images[0].bits_per_component, Some(1));
+        assert_eq!(images[0].color_space.as_deref(), Some("DeviceGray"));
+        let stream = doc.get_object(images[0].id).unwrap().as_stream().unwrap();
+        assert_eq!(
+            stream.decompressed_content().unwrap()[..3],
+            [0x00, 0xFF, 0xFF]
+        );
+
+        // 无损嵌入灰度图像
+        let options = PdfOptions {
+            lossless: true,
+            ..Default::default()
+        };
+        let gray = GrayImage::from_fn(64, 64, |x, _| image::Luma([(x * 4) as u8]));
+        let pdf = options
+            .render(&DynamicImage::ImageLuma8(gray.clone()), None)
+            .unwrap();
+        let doc = Document::load_mem(&pdf).unwrap();
+        let page = doc.get_pages()[&1];
+        let images = doc.get_page_images(page).unwrap();
+        assert_eq!(images[0].bits_per_component, Some(8));
+        assert!(
+            images[0]
+                .filters
+                .as_ref()
+                .is_none_or(|f| !f.contains(&"DCTDecode".to_string()))
+        );
+        let stream = doc.get_object(images[0].id).unwrap().as_stream().unwrap();
+        assert_eq!(stream.decompressed_content().unwrap(), gray.into_raw());
+    }
+
+    #[test]
+    fn test_srgb_profile() {
+        let srgb = srgb_profile().unwrap();
+        assert_eq!(&srgb[24..36], &[7, 208, 0, 1, 0, 1, 0, 0, 0, 0, 0, 0]);
+        let profile = ColorProfile::new_from_slice(&srgb).unwrap();
+        assert_eq!(profile.creation_date_time.year, 2000);
+        assert!(profile.is_matrix_shaper());
+    }
+
+    #[test]
+    fn test_pdfa() {
+        let options = PdfOptions {
+            title: Some("Demo & <Test>".to_string()),
+            rights: Some("CC BY 4.0".to_string()),
+            pdfa: true,
+            ..Default::default()
+        };
+        let image = image::open("./fixtures/demo.jpg").unwrap();
+        let pdf = options.render(&image, None).unwrap();
+        // 文件头之后是二进制注释
+        assert!(pdf.starts_with(b"%PDF-1.7\n%"));
+        assert!(pdf[10..14].iter().all(|&b| b >= 128));
+        let doc = Document::load_mem(&pdf).unwrap();
+
+        let id = doc.trailer.get(b"ID").unwrap().as_array().unwrap();
+        assert_eq!(id.len(), 2);
+        assert_eq!(id[0].as_str().unwrap().len(), 16);
+        assert_eq!(id[0], id[1]);
+
+        // 文件标识只取决于文档内容
+        let same = Document::load_mem(&options.render(&image, None).unwrap()).unwrap();
+        assert_eq!(same.trailer.get(b"ID").unwrap().as_array().unwrap(), id);
+        let retitled = PdfOptions {
+            title: Some("Other".to_string()),
+            ..options.clone()
+        };
+        let other = Document::load_mem(&retitled.render(&image, None).unwrap()).unwrap();
+        assert_ne!(other.trailer.get(b"ID").unwrap().as_array().unwrap(), id);
+
+        // XMP 元数据声明 PDF/A-2b，且与文档信息一致
+        let catalog = doc.catalog().unwrap();
+        let metadata = catalog.get(b"Metadata").unwrap().as_reference().unwrap();
+        let metadata = doc.get_object(metadata).unwrap().as_stream().unwrap();
+        assert_eq!(
+            metadata.dict.get(b"Type").unwrap().as_name().unwrap(),
+            b"Metadata"
+        );
+        assert_eq!(
+            metadata.dict.get(b"Subtype").unwrap().as_name().unwrap(),
+            b"XML"
+        );
+        assert!(metadata.dict.get(b"Filter").is_err());
+        let xmp = String::from_utf8(metadata.content.clone()).unwrap();
+        assert!(xmp.starts_with("<?xpacket begin=\"\u{FEFF}\""));
+        assert!(xmp.contains("<pdfaid:part>2</pdfaid:part>"));
+        assert!(xmp.contains("<pdfaid:conformance>B</pdfaid:conformance>"));
+        assert!(xmp.contains("<rdf:li xml:lang=\"x-default\">Demo &amp; &lt;Test&gt;</rdf:li>"));
+        assert!(xmp.contains("<rdf:li xml:lang=\"x-default\">CC BY 4.0</rdf:li>"));
+        let info_id = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        let info = doc.get_dictionary(info_id).unwrap();
+        assert_eq!(
+            info.get(b"Title").unwrap().as_str().unwrap(),
+            b"Demo & <Test>"
+        );
+
+        // sRGB 输出意图
+        let intents = catalog.get(b"OutputIntents").unwrap().as_array().unwrap();
+        let intent = doc
+            .get_dictionary(intents[0].as_reference().unwrap())
+            .unwrap();
+        assert_eq!(
+            intent.get(b"Type").unwrap().as_name().unwrap(),
+            b"OutputIntent"
+        );
+        assert_eq!(intent.get(b"S").unwrap().as_name().unwrap(), b"GTS_PDFA1");
+        let profile = intent
+            .get(b"DestOutputProfile")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let profile = doc.get_object(profile).unwrap().as_stream().unwrap();
+        assert_eq!(profile.dict.get(b"N").unwrap().as_i64().unwrap(), 3);
+        let profile = ColorProfile::new_from_slice(&profile.content).unwrap();
+        assert_eq!(profile.color_space, moxcms::DataColorSpace::Rgb);
+
+        // 不包含 PDF/A 禁止的功能
+        assert!(doc.trailer.get(b"Encrypt").is_err());
+        for object in doc.objects.values() {
+            let dict = match object {
+                Object::Dictionary(dict) => dict,
+                Object::Stream(stream) => &stream.dict,
+                _ => continue,
+            };
+            for key in [b"JavaScript".as_slice(), b"JS", b"AA", b"EmbeddedFiles"] {
+                assert!(dict.get(key).is_err());
+            }
+            if let Ok(filter) = dict.get(b"Filter") {
+                assert_ne!(filter.as_name().ok(), Some(b"LZWDecode".as_slice()));
+            }
+            assert!(dict.get(b"Interpolate").and_then(Object::as_bool).ok() != Some(true));
+        }
+
+        // 未启用时不生成 PDF/A 结构
+        let pdf = PdfOptions::default().render(&image, None).unwrap();
+        let doc = Document::load_mem(&pdf).unwrap();
+        assert!(doc.catalog().unwrap().get(b"Metadata").is_err());
+        assert!(doc.trailer.get(b"ID").is_err());
+    }
+
+    #[test]
+    fn test_target_rect() {
+        assert_eq!(
+            target_rect("https://example.org/canvas/1#xywh=1,2,3,4"),
+            Some((1.0, 2.0, 3.0, 4.0))
+        );
+        assert_eq!(target_rect("https://example.org/canvas/1#xywh=1,2"), None);
+        assert_eq!(target_rect("https://example.org/canvas/1"), None);
+    }
+}
@@ -2,6 +2,7 @@ use std::{
     fs::File,
     io::{Read, Write},
     path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
 };
 
 use crate::{
@@ -35,8 +36,7 @@ impl Storage for LocalStorage {
     fn get_iiif_file(&self, params: &IiifImage) -> Result<ProcessResult, String> {
         let iiif_path = params.to_string();
         let path = self.iiif_dir.join(iiif_path);
-        println!("iiif_dir: {:?}", self.iiif_dir);
-        println!("path: {:?}", path);
+        tracing::trace!(path = %path.display(), "reading iiif file");
         let mut file = File::open(path).map_err(|e| e.to_string())?;
         let mut bytes = Vec::new();
         file.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
@@ -55,10 +55,36 @@ impl Storage for LocalStorage {
         } else {
             return Err("Failed to get parent directory".to_string());
         }
-        let mut file = File::create(path).map_err(|e| e.to_string())?;
-        file.write_all(data).map_err(|e| e.to_string())?;
+        // 先写入临时文件再重命名，避免读取到未写完的文件
+        let tmp_path = temp_path(&path);
+        let result = File::create(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(data)?;
+                file.sync_all()
+            })
+            .and_then(|_| std::fs::rename(&tmp_path, &path));
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(e.to_string());
+        }
         Ok(())
     }
+
+    /// 原图目录和衍生图目录
+    fn cache_key(&self) -> String {
+        format!("{}:{}", self.origin_dir.display(), self.iiif_dir.display())
+    }
+}
+
+/// 生成与目标文件同目录的临时文件路径
+fn temp_path(path: &Path) -> PathBuf {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{file_name}.{}.{n}.tmp", std::process::id()))
 }
 
 impl LocalStorage {
@@ -91,6 +117,15 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_cache_key() {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let same = LocalStorage::new("./fixtures", "./fixtures/out");
+        let other = LocalStorage::new("./fixtures", "./fixtures/other");
+        assert_eq!(storage.cache_key(), same.cache_key());
+        assert_ne!(storage.cache_key(), other.cache_key());
+    }
+
     #[test]
     fn test_get_iiif_file() {
         let storage = LocalStorage::new("./fixtures", "./fixtures/out");
@@ -113,4 +148,33 @@ mod tests {
         let result = storage.save_iiif_file(&params, &result.data);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_save_iiif_file_atomic() {
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let params = IiifImage {
+            identifier: "demo.jpg".to_string(),
+            region: Region::Rect(0, 0, 10, 10),
+            size: Size::Max,
+            rotation: Rotation::Degrees(0.0),
+            quality: Quality::Default,
+            format: Format::Png,
+        };
+        storage.save_iiif_file(&params, b"first").unwrap();
+        storage.save_iiif_file(&params, b"second").unwrap();
+        let result = storage.get_iiif_file(&params).unwrap();
+        assert_eq!(result.data, b"second");
+
+        // 不应残留临时文件
+        let dir = "./fixtures/out/demo.jpg/0,0,10,10";
+        let leftovers = std::fs::read_dir(format!("{dir}/max/0"))
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().ends_with(".tmp")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
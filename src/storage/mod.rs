@@ -9,4 +9,15 @@ pub trait Storage {
     fn get_iiif_file(&self, params: &IiifImage) -> Result<ProcessResult, String>;
 
     fn save_iiif_file(&self, params: &IiifImage, data: &[u8]) -> Result<(), String>;
+
+    /// 标识存储内容的稳定键，相同键的存储共享正在进行的处理。默认为类型名，
+    /// 同一类型的实例读写不同内容时应返回各自的根路径等
+    ///
+    /// A stable key identifying what the storage reads and writes. Concurrent equivalent
+    /// requests against storages with the same key share one rendering. Defaults to the type
+    /// name; implementations whose instances serve different content should override it,
+    /// for example with their root paths.
+    fn cache_key(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
 }
@@ -0,0 +1,148 @@
+//! 链路追踪与指标(Tracing and metrics.)
+//!
+//! 图片处理的每个阶段都会创建一个 `tracing` span，并通过 `metrics` 门面记录缓存命中、字节数和耗时。
+//! 使用方可以安装任意 `metrics` 兼容的 recorder，开启 `prometheus` feature 后可直接导出 Prometheus 格式。
+//!
+//! Every stage of image processing runs inside a `tracing` span, and cache hits, bytes and
+//! latencies are recorded through the `metrics` facade. Install any `metrics` compatible
+//! recorder to collect them, or enable the `prometheus` feature for a ready-made exporter.
+
+use std::time::Instant;
+
+/// 缓存命中次数
+///
+/// Number of requests answered from previously saved derivatives.
+pub const CACHE_HITS: &str = "iiif_cache_hits_total";
+
+/// 缓存未命中次数
+///
+/// Number of requests that had to be generated from the origin file.
+pub const CACHE_MISSES: &str = "iiif_cache_misses_total";
+
+/// 读取的原始文件字节数
+///
+/// Bytes read from origin files.
+pub const ORIGIN_BYTES: &str = "iiif_origin_bytes_total";
+
+/// 返回的图片字节数，`source` 标签为 `cache` 或 `generated`
+///
+/// Bytes of image data returned, labelled by `source` (`cache` or `generated`).
+pub const RESPONSE_BYTES: &str = "iiif_response_bytes_total";
+
+/// 各阶段耗时（秒），`stage` 标签为阶段名称
+///
+/// Duration of each processing stage in seconds, labelled by `stage`.
+pub const STAGE_DURATION: &str = "iiif_stage_duration_seconds";
+
+/// 整个请求的处理耗时（秒）
+///
+/// Duration of a whole request in seconds, labelled by `source`.
+pub const REQUEST_DURATION: &str = "iiif_request_duration_seconds";
+
+/// 图片处理阶段
+///
+/// A stage of the image processing pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// 读取原始文件
+    Fetch,
+    /// 解码原始文件
+    Decode,
+    /// 裁剪区域
+    Region,
+    /// 缩放尺寸
+    Size,
+    /// 色彩管理
+    Color,
+    /// 旋转
+    Rotation,
+    /// 画质转换
+    Quality,
+    /// 编码输出格式
+    Encode,
+    /// 保存衍生文件
+    Save,
+}
+
+impl Stage {
+    /// 阶段名称，用作 span 名称和指标标签
+    ///
+    /// The stage name used for span fields and metric labels.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Stage::Fetch => "fetch",
+            Stage::Decode => "decode",
+            Stage::Region => "region",
+            Stage::Size => "size",
+            Stage::Color => "color",
+            Stage::Rotation => "rotation",
+            Stage::Quality => "quality",
+            Stage::Encode => "encode",
+            Stage::Save => "save",
+        }
+    }
+
+    /// 在该阶段的 span 中执行 `f` 并记录耗时
+    pub(crate) fn run<T>(self, f: impl FnOnce() -> T) -> T {
+        let _span = tracing::debug_span!("iiif_stage", stage = self.as_str()).entered();
+        let start = Instant::now();
+        let result = f();
+        let elapsed = start.elapsed();
+        metrics::histogram!(STAGE_DURATION, "stage" => self.as_str()).record(elapsed.as_secs_f64());
+        tracing::trace!(
+            elapsed_ms = elapsed.as_secs_f64() * 1000.0,
+            "stage finished"
+        );
+        result
+    }
+}
+
+/// 安装全局 Prometheus recorder，返回用于渲染指标文本的句柄
+///
+/// Installs a global Prometheus recorder and returns the handle used to render the
+/// exposition text, e.g. from a `/metrics` endpoint.
+///
+/// Example:
+/// ```no_run
+/// let handle = i3f::telemetry::install_prometheus_recorder().unwrap();
+/// println!("{}", handle.render());
+/// ```
+#[cfg(feature = "prometheus")]
+pub fn install_prometheus_recorder()
+-> Result<metrics_exporter_prometheus::PrometheusHandle, crate::IiifError> {
+    metrics_exporter_prometheus::PrometheusBuilder::new()
+        .install_recorder()
+        .map_err(|e| crate::IiifError::InternalServerError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_as_str() {
+        assert_eq!(Stage::Fetch.as_str(), "fetch");
+        assert_eq!(Stage::Encode.as_str(), "encode");
+        assert_eq!(Stage::Save.run(|| 1 + 1), 2);
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn test_prometheus_metrics() {
+        use crate::image::IiifImage;
+        use crate::storage::LocalStorage;
+
+        let recorder = metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        let storage = LocalStorage::new("./fixtures", "./fixtures/out");
+        let url =
+            url::Url::parse("https://example.org/image-service/demo.jpg/full/max/0/default.jpg")
+                .unwrap();
+        let image = IiifImage::try_from(url).unwrap();
+        metrics::with_local_recorder(&recorder, || image.process(&storage).unwrap());
+
+        let output = handle.render();
+        assert!(output.contains(CACHE_HITS));
+        assert!(output.contains(RESPONSE_BYTES));
+    }
+}